@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+
+use crate::types::*;
+
+/// Builds a [`TrackPoint`] with fluent setters, the fields which aren't set stay `None`.
+/// ```
+/// use chrono::Utc;
+///
+/// let tp = quick_tcx::TrackPoint::builder()
+///     .time(Utc::now())
+///     .position(51.752415, 39.18763)
+///     .heart_rate(120)
+///     .build();
+/// assert_eq!(Some(120), tp.heart_rate_bpm);
+/// ```
+#[derive(Debug, Default)]
+pub struct TrackPointBuilder {
+    track_point: TrackPoint,
+}
+
+impl TrackPointBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn time(mut self, time: DateTime<Utc>) -> Self {
+        self.track_point.time = Some(time);
+        self
+    }
+
+    pub fn position(mut self, latitude_degrees: f64, longitude_degrees: f64) -> Self {
+        self.track_point.position = Some(Position {
+            latitude_degrees,
+            longitude_degrees,
+        });
+        self
+    }
+
+    pub fn altitude(mut self, altitude_meters: f64) -> Self {
+        self.track_point.altitude_meters = Some(altitude_meters);
+        self
+    }
+
+    pub fn distance(mut self, distance_meters: f64) -> Self {
+        self.track_point.distance_meters = Some(distance_meters);
+        self
+    }
+
+    pub fn heart_rate(mut self, heart_rate_bpm: u8) -> Self {
+        self.track_point.heart_rate_bpm = Some(heart_rate_bpm);
+        self
+    }
+
+    pub fn cadence(mut self, cadence: u8) -> Self {
+        self.track_point.cadence = Some(cadence);
+        self
+    }
+
+    pub fn build(self) -> TrackPoint {
+        self.track_point
+    }
+}
+
+impl TrackPoint {
+    pub fn builder() -> TrackPointBuilder {
+        TrackPointBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn track_point_builder_test() {
+        let time = Utc.with_ymd_and_hms(2020, 12, 28, 13, 36, 17).unwrap();
+        let tp = TrackPointBuilder::new()
+            .time(time)
+            .position(51.752415, 39.18763)
+            .altitude(178.6)
+            .heart_rate(68)
+            .cadence(90)
+            .build();
+        assert_eq!(Some(time), tp.time);
+        assert_eq!(
+            Some(Position {
+                latitude_degrees: 51.752415,
+                longitude_degrees: 39.18763,
+            }),
+            tp.position
+        );
+        assert_eq!(Some(178.6), tp.altitude_meters);
+        assert_eq!(Some(68), tp.heart_rate_bpm);
+        assert_eq!(Some(90), tp.cadence);
+        assert_eq!(None, tp.distance_meters);
+        assert_eq!(None, tp.sensor_state);
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::{BufRead, Write};
+
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use thiserror::Error;
+
+use crate::read::{read_activity, read_activity_attributes, ReadContext, ReadError};
+use crate::types::Activity;
+
+#[derive(Error, Debug)]
+pub enum FilterError {
+    #[error("error reading TCX '{0}'")]
+    ReadError(#[from] ReadError),
+    #[error("error writing XML '{0}'")]
+    XmlWriteError(#[from] quick_xml::Error),
+}
+
+/// Copies TCX xml data from `buf_reader` to `writer` leaving out the activities for which
+/// `keep` returns `false`. Activities are read one at a time, the rest of the document
+/// is passed through as is.
+/// ```
+/// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+/// let mut filtered = Vec::new();
+/// quick_tcx::filter_activities(tcx_bytes, &mut filtered, |a| a.is_biking()).unwrap();
+/// let tc_db = quick_tcx::read(filtered.as_slice()).unwrap();
+/// assert!(tc_db.activity_list.unwrap().activities.is_empty());
+/// ```
+pub fn filter_activities<B: BufRead, W: Write>(
+    buf_reader: B,
+    writer: W,
+    keep: impl Fn(&Activity) -> bool,
+) -> Result<(), FilterError> {
+    let mut reader = Reader::from_reader(buf_reader);
+    let mut writer = Writer::new(writer);
+    let mut ctx = ReadContext::default();
+    let mut buf = Vec::new();
+    // depth of the current element inside <Activities>, activities are its direct children
+    let mut activities_depth: Option<usize> = None;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e))
+                if activities_depth == Some(0) && e.name().into_inner() == b"Activity" =>
+            {
+                let mut activity_writer = Writer::new(Vec::new());
+                activity_writer.write_event(Event::Start(e.borrow()))?;
+                copy_element(&mut reader, &mut activity_writer)?;
+                let activity_xml = activity_writer.into_inner();
+                let mut activity_reader = Reader::from_reader(activity_xml.as_slice());
+                let mut activity_buf = Vec::new();
+                let activity = match activity_reader.read_event_into(&mut activity_buf) {
+                    Ok(Event::Start(ref e)) => {
+                        read_activity(&mut activity_reader, b"Activity", e, &mut ctx)?
+                    }
+                    _ => unreachable!("activity starts with the copied start tag"),
+                };
+                if keep(&activity) {
+                    writer
+                        .get_mut()
+                        .write_all(&activity_xml)
+                        .map_err(|e| FilterError::XmlWriteError(quick_xml::Error::Io(e.into())))?;
+                }
+            }
+            Ok(Event::Empty(ref e))
+                if activities_depth == Some(0) && e.name().into_inner() == b"Activity" =>
+            {
+                if keep(&read_activity_attributes(&reader, e)?) {
+                    writer.write_event(Event::Empty(e.borrow()))?;
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(event) => {
+                match &event {
+                    Event::Start(e) => {
+                        activities_depth = match activities_depth {
+                            Some(depth) => Some(depth + 1),
+                            None if e.name().into_inner() == b"Activities" => Some(0),
+                            None => None,
+                        }
+                    }
+                    Event::End(_) => {
+                        activities_depth = activities_depth.and_then(|d| d.checked_sub(1))
+                    }
+                    _ => (),
+                }
+                writer.write_event(event)?;
+            }
+            Err(e) => return Err(FilterError::ReadError(ReadError::XmlReadError(e))),
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// Copies the events up to the end of the element which start tag was just read.
+fn copy_element<B: BufRead, W: Write>(
+    reader: &mut Reader<B>,
+    writer: &mut Writer<W>,
+) -> Result<(), FilterError> {
+    let mut buf = Vec::new();
+    let mut depth = 0;
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(ReadError::XmlReadError)?;
+        match &event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => {
+                writer.write_event(event)?;
+                return Ok(());
+            }
+            Event::End(_) => depth -= 1,
+            Event::Eof => return Err(FilterError::ReadError(ReadError::UnexpectedEof)),
+            _ => (),
+        }
+        writer.write_event(event)?;
+        buf.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_activities_test() {
+        let tcx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase>
+    <Activities>
+        <Activity Sport="Running">
+            <Id>2020-12-28T13:36:16Z</Id>
+            <Notes>Easy &amp; slow</Notes>
+        </Activity>
+        <Activity Sport="Biking"><Id>2020-12-29T13:36:16Z</Id></Activity>
+        <Activity Sport="Biking"/>
+        <Activity Sport="Running"><Id>2020-12-30T13:36:16Z</Id></Activity>
+    </Activities>
+    <Author xsi:type="Device_t">
+        <Name>Forerunner 945</Name>
+        <UnitId>0</UnitId>
+        <ProductID>3113</ProductID>
+        <Version><VersionMajor>1</VersionMajor><VersionMinor>0</VersionMinor></Version>
+    </Author>
+</TrainingCenterDatabase>"#;
+        let mut filtered = Vec::new();
+        filter_activities(tcx.as_bytes(), &mut filtered, |a| !a.is_biking()).unwrap();
+        let tc_db = crate::read(filtered.as_slice()).unwrap();
+        let activities = tc_db.activity_list.unwrap().activities;
+        assert_eq!(2, activities.len());
+        assert!(activities.iter().all(|a| a.is_running()));
+        assert_eq!(Some("Easy & slow".to_string()), activities[0].notes);
+        assert!(tc_db.author.is_some());
+        let filtered = String::from_utf8(filtered).unwrap();
+        assert!(filtered.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(!filtered.contains("Biking"));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::types::{Activity, Position};
+
+/// Mean Earth radius in meters used for great-circle calculations.
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Precision of the encoded polylines used by Google Maps, i.e. 5 decimal places.
+pub const DEFAULT_POLYLINE_PRECISION: u32 = 5;
+
+impl Position {
+    /// Great-circle distance in meters to the `other` position calculated with
+    /// the haversine formula.
+    pub fn distance_to(&self, other: &Position) -> f64 {
+        let lat1 = self.latitude_degrees.to_radians();
+        let lat2 = other.latitude_degrees.to_radians();
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude_degrees - self.longitude_degrees).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+
+    /// Point halfway along the great-circle path to the `other` position.
+    pub fn midpoint(&self, other: &Position) -> Position {
+        let lat1 = self.latitude_degrees.to_radians();
+        let lat2 = other.latitude_degrees.to_radians();
+        let lon1 = self.longitude_degrees.to_radians();
+        let d_lon = (other.longitude_degrees - self.longitude_degrees).to_radians();
+        let bx = lat2.cos() * d_lon.cos();
+        let by = lat2.cos() * d_lon.sin();
+        let lat = (lat1.sin() + lat2.sin()).atan2(((lat1.cos() + bx).powi(2) + by.powi(2)).sqrt());
+        let lon = lon1 + by.atan2(lat1.cos() + bx);
+        Position {
+            latitude_degrees: lat.to_degrees(),
+            longitude_degrees: (lon.to_degrees() + 540.0) % 360.0 - 180.0,
+        }
+    }
+
+    /// Initial bearing in degrees clockwise from north, in the range `[0, 360)`,
+    /// of the great-circle path to the `other` position.
+    pub fn bearing_to(&self, other: &Position) -> f64 {
+        let lat1 = self.latitude_degrees.to_radians();
+        let lat2 = other.latitude_degrees.to_radians();
+        let d_lon = (other.longitude_degrees - self.longitude_degrees).to_radians();
+        let y = d_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_lon.cos();
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+}
+
+impl Activity {
+    /// Positions of the track points encoded in the
+    /// [encoded polyline](https://developers.google.com/maps/documentation/utilities/polylinealgorithm)
+    /// format with `precision` decimal places, see [`DEFAULT_POLYLINE_PRECISION`].
+    /// Track points without a position are skipped.
+    pub fn encoded_polyline(&self, precision: u32) -> String {
+        let factor = 10_f64.powi(precision as i32);
+        let mut polyline = String::new();
+        let (mut prev_lat, mut prev_lon) = (0, 0);
+        for pos in self.track_points().filter_map(|tp| tp.position.as_ref()) {
+            let lat = (pos.latitude_degrees * factor).round() as i64;
+            let lon = (pos.longitude_degrees * factor).round() as i64;
+            encode_polyline_value(lat - prev_lat, &mut polyline);
+            encode_polyline_value(lon - prev_lon, &mut polyline);
+            prev_lat = lat;
+            prev_lon = lon;
+        }
+        polyline
+    }
+}
+
+fn encode_polyline_value(value: i64, polyline: &mut String) {
+    let mut value = if value < 0 { !(value << 1) } else { value << 1 };
+    while value >= 0x20 {
+        polyline.push((((value & 0x1f) | 0x20) as u8 + 63) as char);
+        value >>= 5;
+    }
+    polyline.push((value as u8 + 63) as char);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{ActivityLap, TrackPoint};
+
+    use super::*;
+
+    fn decode_polyline(polyline: &str, precision: u32) -> Vec<(f64, f64)> {
+        let factor = 10_f64.powi(precision as i32);
+        let mut values = Vec::new();
+        let (mut value, mut shift) = (0_i64, 0);
+        for b in polyline.bytes() {
+            let chunk = (b - 63) as i64;
+            value |= (chunk & 0x1f) << shift;
+            shift += 5;
+            if chunk < 0x20 {
+                values.push(if value & 1 == 1 {
+                    !(value >> 1)
+                } else {
+                    value >> 1
+                });
+                value = 0;
+                shift = 0;
+            }
+        }
+        let (mut lat, mut lon) = (0, 0);
+        values
+            .chunks(2)
+            .map(|c| {
+                lat += c[0];
+                lon += c[1];
+                (lat as f64 / factor, lon as f64 / factor)
+            })
+            .collect()
+    }
+
+    fn activity(coordinates: &[(f64, f64)]) -> Activity {
+        let mut track_points: Vec<TrackPoint> = coordinates
+            .iter()
+            .map(|&(latitude_degrees, longitude_degrees)| TrackPoint {
+                position: Some(Position {
+                    latitude_degrees,
+                    longitude_degrees,
+                }),
+                ..TrackPoint::default()
+            })
+            .collect();
+        track_points.insert(1, TrackPoint::default());
+        Activity {
+            laps: vec![ActivityLap {
+                track_points,
+                ..ActivityLap::default()
+            }],
+            ..Activity::default()
+        }
+    }
+
+    #[test]
+    fn distance_to_test() {
+        let a = Position {
+            latitude_degrees: 51.752415,
+            longitude_degrees: 39.18763,
+        };
+        let b = Position {
+            latitude_degrees: 51.761415,
+            longitude_degrees: 39.18763,
+        };
+        assert_eq!(0.0, a.distance_to(&a));
+        // 0.009 degrees of latitude are roughly one kilometer
+        assert!((a.distance_to(&b) - 1000.75).abs() < 0.5);
+        assert_eq!(a.distance_to(&b), b.distance_to(&a));
+    }
+
+    #[test]
+    fn midpoint_test() {
+        let a = Position {
+            latitude_degrees: 0.0,
+            longitude_degrees: 0.0,
+        };
+        let b = Position {
+            latitude_degrees: 0.0,
+            longitude_degrees: 90.0,
+        };
+        let m = a.midpoint(&b);
+        assert!(m.latitude_degrees.abs() < 1e-9);
+        assert!((m.longitude_degrees - 45.0).abs() < 1e-9);
+
+        let london = Position {
+            latitude_degrees: 51.5074,
+            longitude_degrees: -0.1278,
+        };
+        let paris = Position {
+            latitude_degrees: 48.8566,
+            longitude_degrees: 2.3522,
+        };
+        let m = london.midpoint(&paris);
+        assert!((m.distance_to(&london) - m.distance_to(&paris)).abs() < 1e-3);
+        assert!((m.latitude_degrees - 50.1886).abs() < 1e-3);
+        assert!((m.longitude_degrees - 1.1466).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bearing_to_test() {
+        let a = Position {
+            latitude_degrees: 51.752415,
+            longitude_degrees: 39.18763,
+        };
+        let north = Position {
+            latitude_degrees: 51.761415,
+            longitude_degrees: 39.18763,
+        };
+        let west = Position {
+            latitude_degrees: 0.0,
+            longitude_degrees: -10.0,
+        };
+        let east = Position {
+            latitude_degrees: 0.0,
+            longitude_degrees: 10.0,
+        };
+        assert!(a.bearing_to(&north).abs() < 1e-9);
+        assert!((north.bearing_to(&a) - 180.0).abs() < 1e-9);
+        assert!((west.bearing_to(&east) - 90.0).abs() < 1e-9);
+        assert!((east.bearing_to(&west) - 270.0).abs() < 1e-9);
+        // Baghdad to Osaka, a textbook example
+        let baghdad = Position {
+            latitude_degrees: 35.0,
+            longitude_degrees: 45.0,
+        };
+        let osaka = Position {
+            latitude_degrees: 35.0,
+            longitude_degrees: 135.0,
+        };
+        assert!((baghdad.bearing_to(&osaka) - 60.16).abs() < 0.01);
+    }
+
+    #[test]
+    fn encoded_polyline_test() {
+        let coordinates = [(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)];
+        let activity = activity(&coordinates);
+        assert_eq!(
+            "_p~iF~ps|U_ulLnnqC_mqNvxq`@",
+            activity.encoded_polyline(DEFAULT_POLYLINE_PRECISION)
+        );
+    }
+
+    #[test]
+    fn encoded_polyline_decode_test() {
+        let coordinates = [
+            (51.752415, 39.18763),
+            (51.752781, 39.188144),
+            (-33.856784, 151.215297),
+        ];
+        let activity = activity(&coordinates);
+        for precision in [5, 6] {
+            let decoded = decode_polyline(&activity.encoded_polyline(precision), precision);
+            assert_eq!(coordinates.len(), decoded.len());
+            let tolerance = 0.5 / 10_f64.powi(precision as i32) + 1e-12;
+            for (c, d) in coordinates.iter().zip(decoded.iter()) {
+                assert!((c.0 - d.0).abs() <= tolerance);
+                assert!((c.1 - d.1).abs() <= tolerance);
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use quick_xml::events::{BytesDecl, Event};
+use quick_xml::{Result, Writer};
+
+use crate::types::*;
+use crate::write::{format_time, sport_name, write_opt_value, write_text, WriteError};
+
+const GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+
+impl Activity {
+    /// Writes the activity as a GPX 1.1 track into a string, see [`write_gpx`](crate::write_gpx).
+    /// ```
+    /// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+    /// let tc_db = quick_tcx::read(tcx_bytes).unwrap();
+    /// let gpx = tc_db.activity_list.unwrap().activities[0].to_gpx_string().unwrap();
+    /// assert!(gpx.contains("<trk>"));
+    /// ```
+    pub fn to_gpx_string(&self) -> std::result::Result<String, WriteError> {
+        let mut bytes = Vec::new();
+        crate::write_gpx(self, &mut bytes)?;
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+/// Writes the activity as a GPX 1.1 track. Track points without a position are left out,
+/// since GPX requires one.
+pub(crate) fn write_gpx_activity<W: Write>(
+    writer: &mut Writer<W>,
+    activity: &Activity,
+) -> Result<()> {
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("gpx")
+        .with_attribute(("version", "1.1"))
+        .with_attribute(("creator", "quick_tcx"))
+        .with_attribute(("xmlns", GPX_NAMESPACE))
+        .write_inner_content(|w| {
+            w.create_element("metadata")
+                .write_inner_content(|w| write_text(w, "time", &format_time(&activity.id)))?;
+            w.create_element("trk").write_inner_content(|w| {
+                if let Some(notes) = &activity.notes {
+                    write_text(w, "desc", notes)?;
+                }
+                write_text(w, "type", sport_name(&activity.sport))?;
+                for lap in &activity.laps {
+                    w.create_element("trkseg").write_inner_content(|w| {
+                        for tp in &lap.track_points {
+                            if let Some(position) = &tp.position {
+                                write_gpx_track_point(w, tp, position)?;
+                            }
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_gpx_track_point<W: Write>(
+    writer: &mut Writer<W>,
+    tp: &TrackPoint,
+    position: &Position,
+) -> Result<()> {
+    writer
+        .create_element("trkpt")
+        .with_attribute(("lat", position.latitude_degrees.to_string().as_str()))
+        .with_attribute(("lon", position.longitude_degrees.to_string().as_str()))
+        .write_inner_content(|w| {
+            write_opt_value(w, "ele", &tp.altitude_meters)?;
+            match &tp.time {
+                Some(time) => write_text(w, "time", &format_time(time)),
+                None => Ok(()),
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn write_gpx_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let tc_db = crate::read(tcx_bytes).unwrap();
+        let activity = &tc_db.activity_list.unwrap().activities[0];
+        let mut written = Vec::new();
+        crate::write_gpx(activity, &mut written).unwrap();
+        let gpx = String::from_utf8(written).unwrap();
+        assert!(gpx.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(gpx.contains("<type>Running</type>"));
+        assert_eq!(activity.laps.len(), gpx.matches("<trkseg>").count());
+        let positions = activity
+            .track_points()
+            .filter(|tp| tp.position.is_some())
+            .count();
+        assert_eq!(positions, gpx.matches("<trkpt ").count());
+    }
+
+    #[test]
+    fn to_gpx_string_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let tc_db = crate::read(tcx_bytes).unwrap();
+        let activity = &tc_db.activity_list.unwrap().activities[0];
+        let gpx = activity.to_gpx_string().unwrap();
+        assert!(gpx.contains("<trk>"));
+        let first = activity
+            .track_points()
+            .find_map(|tp| tp.position.as_ref())
+            .unwrap();
+        assert!(gpx.contains(&format!(
+            r#"<trkpt lat="{}" lon="{}">"#,
+            first.latitude_degrees, first.longitude_degrees
+        )));
+    }
+}
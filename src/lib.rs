@@ -1,15 +1,28 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::io::BufRead;
+use std::convert::TryFrom;
+use std::io::{BufRead, Write};
 
-use quick_xml::Reader;
+use quick_xml::{Reader, Writer};
 
-pub use read::ReadError;
+pub use builder::TrackPointBuilder;
+pub use filter::{filter_activities, FilterError};
+pub use geo::DEFAULT_POLYLINE_PRECISION;
+pub use metrics::{LapSplit, LapTotals, Sample, SegmentMetrics};
+pub use read::{Coercions, ElementEvent, ReadError, ReadOptions, ReadStats, TrackPointFields};
 pub use types::*;
+pub use write::WriteError;
 
+mod builder;
+mod filter;
+mod geo;
+mod gpx;
+mod metrics;
 mod read;
+mod transform;
 mod types;
+mod write;
 
 /// Read the content of TCX xml data into TrainingCenterDatabase structure
 /// ```
@@ -17,6 +30,112 @@ mod types;
 /// assert_eq!(true, quick_tcx::read(tcx_bytes).is_ok());
 /// ```
 pub fn read<B: BufRead>(buf_reader: B) -> Result<TrainingCenterDatabase, ReadError> {
+    read_with_options(buf_reader, &ReadOptions::default())
+}
+
+/// Read the content of TCX xml string into TrainingCenterDatabase structure
+/// ```
+/// let tcx = include_str!("../test_resources/test.tcx.xml");
+/// assert_eq!(true, quick_tcx::read_str(tcx).is_ok());
+/// ```
+pub fn read_str(tcx: &str) -> Result<TrainingCenterDatabase, ReadError> {
+    read(tcx.as_bytes())
+}
+
+/// Read the content of TCX xml data into TrainingCenterDatabase structure
+/// according to the provided options
+/// ```
+/// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+/// let options = quick_tcx::ReadOptions {
+///     skip_track_points: true,
+///     ..quick_tcx::ReadOptions::default()
+/// };
+/// let tc_db = quick_tcx::read_with_options(tcx_bytes, &options).unwrap();
+/// let activity = &tc_db.activity_list.unwrap().activities[0];
+/// assert_eq!(true, activity.laps[0].track_points.is_empty());
+/// ```
+pub fn read_with_options<B: BufRead>(
+    buf_reader: B,
+    options: &ReadOptions,
+) -> Result<TrainingCenterDatabase, ReadError> {
+    read_with_stats(buf_reader, options).map(|(tc_db, _)| tc_db)
+}
+
+/// Read the content of TCX xml data into TrainingCenterDatabase structure
+/// according to the provided options, together with the counts of the read data
+/// and of the values fixed up in lenient mode
+/// ```
+/// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+/// let options = quick_tcx::ReadOptions::default();
+/// let (_, stats) = quick_tcx::read_with_stats(tcx_bytes, &options).unwrap();
+/// assert_eq!(1, stats.activities);
+/// assert_eq!(10, stats.laps);
+/// assert_eq!(0, stats.coercions.cadence_clamped);
+/// ```
+pub fn read_with_stats<B: BufRead>(
+    buf_reader: B,
+    options: &ReadOptions,
+) -> Result<(TrainingCenterDatabase, ReadStats), ReadError> {
+    let mut ctx = read::ReadContext::new(options.clone());
     let mut reader = Reader::from_reader(buf_reader);
-    read::read_training_center(&mut reader)
+    let tc_db = read::read_training_center(&mut reader, &mut ctx)?;
+    Ok((tc_db, ctx.stats))
+}
+
+/// Read the content of several TCX xml documents concatenated in one stream,
+/// one TrainingCenterDatabase structure per document
+/// ```
+/// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+/// let stream = [tcx_bytes, tcx_bytes].concat();
+/// let tc_dbs = quick_tcx::read_many(stream.as_slice()).unwrap();
+/// assert_eq!(2, tc_dbs.len());
+/// ```
+pub fn read_many<B: BufRead>(buf_reader: B) -> Result<Vec<TrainingCenterDatabase>, ReadError> {
+    let mut reader = Reader::from_reader(buf_reader);
+    let mut ctx = read::ReadContext::new(ReadOptions::default());
+    read::read_training_centers(&mut reader, &mut ctx)
+}
+
+/// Write TrainingCenterDatabase structure as TCX xml data
+/// ```
+/// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+/// let tc_db = quick_tcx::read(tcx_bytes).unwrap();
+/// let mut written = Vec::new();
+/// quick_tcx::write(&tc_db, &mut written).unwrap();
+/// assert_eq!(tc_db, quick_tcx::read(written.as_slice()).unwrap());
+/// ```
+pub fn write<W: Write>(tc_db: &TrainingCenterDatabase, writer: W) -> Result<(), WriteError> {
+    let mut writer = Writer::new_with_indent(writer, b' ', 2);
+    write::write_training_center(&mut writer, tc_db)?;
+    Ok(())
+}
+
+/// Write the Activity as a GPX 1.1 track, with a track segment per lap
+/// ```
+/// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+/// let tc_db = quick_tcx::read(tcx_bytes).unwrap();
+/// let mut written = Vec::new();
+/// quick_tcx::write_gpx(&tc_db.activity_list.unwrap().activities[0], &mut written).unwrap();
+/// assert!(String::from_utf8(written).unwrap().contains("<trkseg>"));
+/// ```
+pub fn write_gpx<W: Write>(activity: &Activity, writer: W) -> Result<(), WriteError> {
+    let mut writer = Writer::new_with_indent(writer, b' ', 2);
+    gpx::write_gpx_activity(&mut writer, activity)?;
+    Ok(())
+}
+
+impl TryFrom<&[u8]> for TrainingCenterDatabase {
+    type Error = ReadError;
+
+    /// Read the TCX xml bytes into TrainingCenterDatabase structure
+    /// ```
+    /// use std::convert::TryFrom;
+    ///
+    /// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+    /// let tc_db = quick_tcx::TrainingCenterDatabase::try_from(tcx_bytes).unwrap();
+    /// assert_eq!(1, tc_db.activity_list.unwrap().activities.len());
+    /// ```
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        read(bytes)
+    }
 }
@@ -0,0 +1,1233 @@
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+use std::ops::AddAssign;
+
+use chrono::{DateTime, Utc};
+
+use crate::types::*;
+
+/// Row of a splits table, see [`Activity::lap_splits`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LapSplit {
+    pub lap_index: usize,
+    /// Distance of the lap and all laps before it in meters.
+    pub cumulative_distance_meters: f64,
+    /// Duration of the lap and all laps before it in seconds.
+    pub cumulative_time_seconds: f64,
+    pub distance_meters: f64,
+    pub time_seconds: f64,
+    /// Pace in seconds per kilometer, `None` for a lap without distance.
+    pub pace_seconds_per_km: Option<f64>,
+}
+
+/// Summary of the track points recorded within a time window, see [`Activity::segment_metrics`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SegmentMetrics {
+    pub distance_meters: f64,
+    /// Time between the first and the last track point of the segment in seconds.
+    pub elapsed_time_seconds: f64,
+    /// Average heart rate of the track points, `None` if none of them has heart rate.
+    pub average_heart_rate_bpm: Option<f64>,
+    /// Average speed in meters per second, `None` for a segment without duration.
+    pub average_speed_mps: Option<f64>,
+    pub elevation_gain_meters: f64,
+}
+
+/// Track point flattened into a table row, see [`Activity::samples`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub time: Option<DateTime<Utc>>,
+    pub latitude_degrees: Option<f64>,
+    pub longitude_degrees: Option<f64>,
+    pub altitude_meters: Option<f64>,
+    pub distance_meters: Option<f64>,
+    pub heart_rate_bpm: Option<u8>,
+    /// [Effective cadence](TrackPoint::effective_cadence) of the track point.
+    pub cadence: Option<u8>,
+    /// Speed in meters per second from the `Speed` extension.
+    pub speed: Option<f64>,
+    /// Power from the `Watts` extension.
+    pub watts: Option<u16>,
+}
+
+/// Totals of several laps, built by adding laps one by one or by collecting them:
+///
+/// ```
+/// use quick_tcx::{ActivityLap, LapTotals};
+///
+/// let laps = vec![ActivityLap::default(), ActivityLap::default()];
+/// let totals: LapTotals = laps.iter().collect();
+/// assert_eq!(2, totals.laps);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LapTotals {
+    pub laps: usize,
+    pub total_time_seconds: f64,
+    pub distance_meters: f64,
+    pub calories: u32,
+    /// Highest `MaximumSpeed` of the laps, `None` if none of them has it.
+    pub maximum_speed: Option<f64>,
+    /// Highest `MaximumHeartRateBpm` of the laps, `None` if none of them has it.
+    pub maximum_heart_rate_bpm: Option<u8>,
+}
+
+impl AddAssign<&ActivityLap> for LapTotals {
+    fn add_assign(&mut self, lap: &ActivityLap) {
+        self.laps += 1;
+        self.total_time_seconds += lap.total_time_seconds;
+        self.distance_meters += lap.distance_meters;
+        self.calories += u32::from(lap.calories);
+        if let Some(speed) = lap.maximum_speed {
+            self.maximum_speed = Some(self.maximum_speed.map_or(speed, |s| s.max(speed)));
+        }
+        self.maximum_heart_rate_bpm = self.maximum_heart_rate_bpm.max(lap.maximum_heart_rate_bpm);
+    }
+}
+
+impl<'a> FromIterator<&'a ActivityLap> for LapTotals {
+    fn from_iter<I: IntoIterator<Item = &'a ActivityLap>>(laps: I) -> Self {
+        let mut totals = LapTotals::default();
+        for lap in laps {
+            totals += lap;
+        }
+        totals
+    }
+}
+
+impl From<&TrackPoint> for Sample {
+    fn from(tp: &TrackPoint) -> Self {
+        let extension = tp.extension.as_ref();
+        Self {
+            time: tp.time,
+            latitude_degrees: tp.position.as_ref().map(|p| p.latitude_degrees),
+            longitude_degrees: tp.position.as_ref().map(|p| p.longitude_degrees),
+            altitude_meters: tp.altitude_meters,
+            distance_meters: tp.distance_meters,
+            heart_rate_bpm: tp.heart_rate_bpm,
+            cadence: tp.effective_cadence(),
+            speed: extension.and_then(|e| e.speed),
+            watts: extension.and_then(|e| e.watts),
+        }
+    }
+}
+
+impl Activity {
+    /// Iterates over the track points of all laps in the recorded order.
+    pub fn track_points(&self) -> impl Iterator<Item = &TrackPoint> {
+        self.laps.iter().flat_map(|l| l.track_points.iter())
+    }
+
+    /// Track points of all laps in the recorded order as flat [`Sample`]s.
+    pub fn samples(&self) -> Vec<Sample> {
+        self.track_points().map(Sample::from).collect()
+    }
+
+    /// Grade in percent between every pair of consecutive track points.
+    ///
+    /// The horizontal component is taken from `distance_meters` when both points have it,
+    /// otherwise from the great-circle distance between their positions. A segment without
+    /// altitudes, without horizontal data or without horizontal movement yields `None`.
+    pub fn grade_series(&self) -> Vec<Option<f64>> {
+        let points: Vec<&TrackPoint> = self.track_points().collect();
+        points.windows(2).map(|w| grade(w[0], w[1])).collect()
+    }
+
+    /// Sum of the distances of all laps in meters.
+    pub fn total_distance_meters(&self) -> f64 {
+        self.laps.iter().map(|l| l.distance_meters).sum()
+    }
+
+    /// Sum of the durations of all laps in seconds.
+    pub fn total_time_seconds(&self) -> f64 {
+        self.laps.iter().map(|l| l.total_time_seconds).sum()
+    }
+
+    /// Time and distance of every lap along with the cumulative values up to the lap.
+    pub fn lap_splits(&self) -> Vec<LapSplit> {
+        let mut distance = 0.0;
+        let mut time = 0.0;
+        self.laps
+            .iter()
+            .enumerate()
+            .map(|(lap_index, lap)| {
+                distance += lap.distance_meters;
+                time += lap.total_time_seconds;
+                LapSplit {
+                    lap_index,
+                    cumulative_distance_meters: distance,
+                    cumulative_time_seconds: time,
+                    distance_meters: lap.distance_meters,
+                    time_seconds: lap.total_time_seconds,
+                    pace_seconds_per_km: if lap.distance_meters > 0.0 {
+                        Some(lap.total_time_seconds / (lap.distance_meters / 1000.0))
+                    } else {
+                        None
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Average of the [effective cadence](TrackPoint::effective_cadence) of the track points,
+    /// `None` if no track point has cadence.
+    pub fn average_cadence(&self) -> Option<f64> {
+        let (sum, count) = self
+            .track_points()
+            .filter_map(|tp| tp.effective_cadence())
+            .fold((0_u64, 0_u64), |(sum, count), c| {
+                (sum + u64::from(c), count + 1)
+            });
+        if count == 0 {
+            return None;
+        }
+        Some(sum as f64 / count as f64)
+    }
+
+    /// Maximum of the [effective cadence](TrackPoint::effective_cadence) of the track points.
+    pub fn max_cadence(&self) -> Option<u8> {
+        self.track_points()
+            .filter_map(|tp| tp.effective_cadence())
+            .max()
+    }
+
+    /// Metrics of the track points recorded from `start` to `end` inclusive. The window
+    /// is clamped to the recorded data, so the values describe the track points within it
+    /// and a window without track points yields the default, zeroed metrics.
+    /// Track points without time are left out.
+    pub fn segment_metrics(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> SegmentMetrics {
+        let points: Vec<&TrackPoint> = self
+            .track_points()
+            .filter(|tp| tp.time.is_some_and(|t| t >= start && t <= end))
+            .collect();
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return SegmentMetrics::default(),
+        };
+        let distance_meters = points
+            .windows(2)
+            .filter_map(|w| horizontal_distance(w[0], w[1]))
+            .sum();
+        let elapsed_time_seconds = seconds_between(first, last).unwrap_or(0.0);
+        let (hr_sum, hr_count) = points
+            .iter()
+            .filter_map(|tp| tp.heart_rate_bpm)
+            .fold((0_u64, 0_u64), |(sum, count), hr| {
+                (sum + u64::from(hr), count + 1)
+            });
+        SegmentMetrics {
+            distance_meters,
+            elapsed_time_seconds,
+            average_heart_rate_bpm: if hr_count > 0 {
+                Some(hr_sum as f64 / hr_count as f64)
+            } else {
+                None
+            },
+            average_speed_mps: if elapsed_time_seconds > 0.0 {
+                Some(distance_meters / elapsed_time_seconds)
+            } else {
+                None
+            },
+            elevation_gain_meters: elevation_changes(points.into_iter()).0,
+        }
+    }
+
+    /// Wall-clock duration in seconds from the activity start (`id`) to the last track point.
+    ///
+    /// Unlike [`Activity::total_time_seconds`], which sums the lap durations recorded by the
+    /// device, this includes pauses between and within laps. `None` if there are no track points
+    /// with time.
+    pub fn elapsed_time_seconds(&self) -> Option<f64> {
+        let last = self.track_points().filter_map(|tp| tp.time).last()?;
+        Some((last - self.id).num_milliseconds() as f64 / 1000.0)
+    }
+
+    /// Sum of the altitude increases between consecutive track points in meters.
+    /// Track points without altitude are skipped.
+    pub fn elevation_gain_meters(&self) -> f64 {
+        elevation_changes(self.track_points()).0
+    }
+
+    /// Sum of the altitude decreases between consecutive track points in meters, as a positive value.
+    /// Track points without altitude are skipped.
+    pub fn elevation_loss_meters(&self) -> f64 {
+        elevation_changes(self.track_points()).1
+    }
+
+    /// Seconds spent in every heart rate bin of `bin_size` beats per minute, keyed by the lower
+    /// bound of the bin. Every track point with heart rate counts until the next one with
+    /// heart rate, the points without heart rate or time are skipped. Empty for a zero `bin_size`.
+    pub fn heart_rate_histogram(&self, bin_size: u8) -> BTreeMap<u8, f64> {
+        let mut histogram = BTreeMap::new();
+        if bin_size == 0 {
+            return histogram;
+        }
+        let samples: Vec<(DateTime<Utc>, u8)> = self
+            .track_points()
+            .filter_map(|tp| tp.time.zip(tp.heart_rate_bpm))
+            .collect();
+        for w in samples.windows(2) {
+            let ((from, hr), (to, _)) = (w[0], w[1]);
+            let seconds = (to - from).num_milliseconds() as f64 / 1000.0;
+            *histogram.entry(hr / bin_size * bin_size).or_insert(0.0) += seconds;
+        }
+        histogram
+    }
+
+    /// Heart rate recovery in beats per minute: the drop of the heart rate from its peak to
+    /// the first track point with heart rate at least `window_secs` seconds later.
+    /// `None` if there is no heart rate or the recording ends before the window does.
+    pub fn hr_recovery(&self, window_secs: f64) -> Option<u8> {
+        let (peak_time, peak) = self
+            .track_points()
+            .filter_map(|tp| tp.time.zip(tp.heart_rate_bpm))
+            .fold(
+                None,
+                |max: Option<(DateTime<Utc>, u8)>, (time, hr)| match max {
+                    Some((_, max_hr)) if max_hr >= hr => max,
+                    _ => Some((time, hr)),
+                },
+            )?;
+        let window_end = peak_time + chrono::Duration::milliseconds((window_secs * 1000.0) as i64);
+        let recovered = self
+            .track_points()
+            .filter(|tp| tp.time.is_some_and(|t| t >= window_end))
+            .find_map(|tp| tp.heart_rate_bpm)?;
+        Some(peak.saturating_sub(recovered))
+    }
+
+    /// Checks the track points are ordered by time, otherwise returns the `(lap, point)`
+    /// indices of the points with time earlier than the one of the preceding point.
+    /// Track points without time are skipped.
+    pub fn assert_monotonic_time(&self) -> Result<(), Vec<(usize, usize)>> {
+        let mut decreasing = Vec::new();
+        let mut previous: Option<DateTime<Utc>> = None;
+        for (lap_idx, lap) in self.laps.iter().enumerate() {
+            for (point_idx, tp) in lap.track_points.iter().enumerate() {
+                let time = match tp.time {
+                    Some(time) => time,
+                    None => continue,
+                };
+                if matches!(previous, Some(prev) if time < prev) {
+                    decreasing.push((lap_idx, point_idx));
+                }
+                previous = Some(time);
+            }
+        }
+        if decreasing.is_empty() {
+            Ok(())
+        } else {
+            Err(decreasing)
+        }
+    }
+
+    /// `(lap, point)` indices of the track points reached from the previous valid position
+    /// with a speed above `max_speed_mps`, e.g. GPS glitches teleporting a point far away.
+    ///
+    /// Every point is compared with the last point that is not an outlier itself, so the point
+    /// following a glitch isn't reported. Points without a position or time, or not later than
+    /// the compared point are skipped.
+    pub fn outlier_indices(&self, max_speed_mps: f64) -> Vec<(usize, usize)> {
+        let mut outliers = Vec::new();
+        let mut previous: Option<(&TrackPoint, &Position)> = None;
+        for (lap_idx, lap) in self.laps.iter().enumerate() {
+            for (point_idx, tp) in lap.track_points.iter().enumerate() {
+                let position = match &tp.position {
+                    Some(position) if tp.time.is_some() => position,
+                    _ => continue,
+                };
+                if let Some((prev, prev_position)) = previous {
+                    let seconds = seconds_between(prev, tp).unwrap_or(0.0);
+                    if seconds <= 0.0 {
+                        continue;
+                    }
+                    let meters = prev_position.distance_to(position);
+                    if meters / seconds > max_speed_mps {
+                        outliers.push((lap_idx, point_idx));
+                        continue;
+                    }
+                }
+                previous = Some((tp, position));
+            }
+        }
+        outliers
+    }
+
+    /// Best average power in watts over any `window_secs` long part of the activity.
+    ///
+    /// Every track point with the `Watts` extension holds its power until the next one. The
+    /// windows start at those track points and must be fully covered by the recording, so
+    /// `None` is returned if the power is recorded for less than `window_secs` or
+    /// `window_secs` isn't positive.
+    pub fn best_power(&self, window_secs: f64) -> Option<f64> {
+        if window_secs <= 0.0 {
+            return None;
+        }
+        let samples = timed_series(self, |tp| tp.extension.as_ref()?.watts.map(f64::from));
+        let last_time = samples.last()?.0;
+        let mut best: Option<f64> = None;
+        for (i, &(start, _)) in samples.iter().enumerate() {
+            let end = start + window_secs;
+            if end > last_time {
+                break;
+            }
+            let mut energy = 0.0;
+            for w in samples[i..].windows(2) {
+                let ((t0, watts), (t1, _)) = (w[0], w[1]);
+                energy += watts * (t1.min(end) - t0);
+                if t1 >= end {
+                    break;
+                }
+            }
+            let average = energy / window_secs;
+            best = Some(best.map_or(average, |b| b.max(average)));
+        }
+        best
+    }
+
+    /// Calories burned estimated from the power: the work in kilojoules from the `Watts`
+    /// extension of the track points, each holding until the next one, divided by the
+    /// metabolic `efficiency`, e.g. 0.24, and converted to kilocalories.
+    /// `None` without power or with an `efficiency` that isn't positive.
+    pub fn estimated_calories_from_power(&self, efficiency: f64) -> Option<u32> {
+        if efficiency <= 0.0 {
+            return None;
+        }
+        let samples = timed_series(self, |tp| tp.extension.as_ref()?.watts.map(f64::from));
+        if samples.is_empty() {
+            return None;
+        }
+        let joules: f64 = samples.windows(2).map(|w| w[0].1 * (w[1].0 - w[0].0)).sum();
+        Some((joules / 1000.0 / efficiency / KILOJOULES_PER_KILOCALORIE).round() as u32)
+    }
+
+    /// Banister's training impulse (TRIMP) from the heart rate of the track points, each holding
+    /// until the next one: the minutes spent at every heart rate reserve fraction `r` weighted
+    /// by `r * 0.64 * e^(1.92 * r)` for men and `r * 0.86 * e^(1.67 * r)` for women.
+    /// `None` without heart rate or if `max_hr` isn't above `rest_hr`.
+    pub fn trimp(&self, rest_hr: u8, max_hr: u8, is_male: bool) -> Option<f64> {
+        if max_hr <= rest_hr {
+            return None;
+        }
+        let samples = timed_series(self, |tp| tp.heart_rate_bpm.map(f64::from));
+        if samples.is_empty() {
+            return None;
+        }
+        let (a, b) = if is_male { (0.64, 1.92) } else { (0.86, 1.67) };
+        let reserve = f64::from(max_hr - rest_hr);
+        let trimp = samples
+            .windows(2)
+            .map(|w| {
+                let ((t0, hr), (t1, _)) = (w[0], w[1]);
+                let r = ((hr - f64::from(rest_hr)) / reserve).clamp(0.0, 1.0);
+                (t1 - t0) / 60.0 * r * a * (b * r).exp()
+            })
+            .sum();
+        Some(trimp)
+    }
+
+    /// Shortest time in seconds to cover `distance_m` meters, based on the `distance_meters`
+    /// of the track points and interpolated between them. `None` if the recorded distance is
+    /// shorter than `distance_m` or `distance_m` isn't positive.
+    pub fn best_pace(&self, distance_m: f64) -> Option<f64> {
+        if distance_m <= 0.0 {
+            return None;
+        }
+        let samples = timed_series(self, |tp| tp.distance_meters);
+        let mut best: Option<f64> = None;
+        let mut j = 1;
+        for (i, &(start_time, start_distance)) in samples.iter().enumerate() {
+            let target = start_distance + distance_m;
+            j = j.max(i + 1);
+            while j < samples.len() && samples[j].1 < target {
+                j += 1;
+            }
+            if j == samples.len() {
+                break;
+            }
+            let ((t0, d0), (t1, d1)) = (samples[j - 1], samples[j]);
+            let reached = if d1 > d0 {
+                t0 + (t1 - t0) * (target - d0) / (d1 - d0)
+            } else {
+                t1
+            };
+            let time = reached - start_time;
+            best = Some(best.map_or(time, |b| b.min(time)));
+        }
+        best
+    }
+}
+
+impl ActivityLap {
+    /// Calories burned per kilometer, `None` if the lap has no distance.
+    pub fn calories_per_km(&self) -> Option<f64> {
+        if self.distance_meters == 0.0 {
+            return None;
+        }
+        Some(f64::from(self.calories) / (self.distance_meters / 1000.0))
+    }
+
+    /// Calories burned per hour, `None` if the lap has no duration.
+    pub fn calories_per_hour(&self) -> Option<f64> {
+        if self.total_time_seconds == 0.0 {
+            return None;
+        }
+        Some(f64::from(self.calories) / (self.total_time_seconds / 3600.0))
+    }
+
+    /// Maximum speed of the lap in meters per second: the `MaximumSpeed` of the lap, otherwise
+    /// the one of the lap extension, otherwise the fastest `Speed` extension of the track points.
+    pub fn effective_max_speed(&self) -> Option<f64> {
+        self.maximum_speed
+            .or_else(|| self.extension.as_ref().and_then(|e| e.max_speed))
+            .or_else(|| {
+                self.track_points
+                    .iter()
+                    .filter_map(TrackPoint::speed)
+                    .max_by(f64::total_cmp)
+            })
+    }
+
+    /// Average speed in meters per second computed from the track points: the distance covered
+    /// between them divided by the time from the first to the last one. Useful to cross-check
+    /// the recorded values, `None` if the track points span no time or lack it.
+    pub fn average_speed_from_points(&self) -> Option<f64> {
+        let (first, last) = (self.track_points.first()?, self.track_points.last()?);
+        let seconds = seconds_between(first, last)?;
+        if seconds <= 0.0 {
+            return None;
+        }
+        let distance: f64 = self
+            .track_points
+            .windows(2)
+            .filter_map(|w| horizontal_distance(&w[0], &w[1]))
+            .sum();
+        Some(distance / seconds)
+    }
+
+    /// Sum of the altitude increases between consecutive track points of the lap in meters.
+    pub fn elevation_gain_meters(&self) -> f64 {
+        elevation_changes(self.track_points.iter()).0
+    }
+
+    /// Sum of the altitude decreases between consecutive track points of the lap in meters.
+    pub fn elevation_loss_meters(&self) -> f64 {
+        elevation_changes(self.track_points.iter()).1
+    }
+
+    /// Fraction of the track points with the `Present` sensor state among the track points
+    /// carrying a sensor state, `None` if none of them does.
+    pub fn sensor_present_fraction(&self) -> Option<f64> {
+        let (present, total) = self
+            .track_points
+            .iter()
+            .filter_map(|tp| tp.sensor_state.as_ref())
+            .fold((0, 0), |(present, total), state| match state {
+                SensorState::Present => (present + 1, total + 1),
+                SensorState::Absent => (present, total + 1),
+            });
+        if total == 0 {
+            return None;
+        }
+        Some(f64::from(present) / f64::from(total))
+    }
+}
+
+impl ActivityList {
+    /// Iterates over the activities of the list followed by the activities of its multi-sport sessions.
+    fn all_activities(&self) -> impl Iterator<Item = &Activity> {
+        let multi_sport_activities = self
+            .multi_sport_sessions
+            .iter()
+            .flat_map(|s| s.sports.iter().flatten())
+            .filter_map(|ma| ma.activity.as_ref());
+        self.activities.iter().chain(multi_sport_activities)
+    }
+
+    /// Iterates over the track points of all activities, including the ones of multi-sport
+    /// sessions, along with the activity and the lap they belong to.
+    pub fn track_points_with_context(
+        &self,
+    ) -> impl Iterator<Item = (&Activity, &ActivityLap, &TrackPoint)> {
+        self.all_activities().flat_map(|a| {
+            a.laps
+                .iter()
+                .flat_map(move |l| l.track_points.iter().map(move |tp| (a, l, tp)))
+        })
+    }
+
+    /// Distance in meters of all activities, including the ones of multi-sport sessions.
+    pub fn total_distance_meters(&self) -> f64 {
+        self.all_activities()
+            .map(|a| a.total_distance_meters())
+            .sum()
+    }
+
+    /// Duration in seconds of all activities, including the ones of multi-sport sessions.
+    pub fn total_time_seconds(&self) -> f64 {
+        self.all_activities().map(|a| a.total_time_seconds()).sum()
+    }
+
+    /// Elevation gain in meters of all activities, including the ones of multi-sport sessions.
+    pub fn total_elevation_gain_meters(&self) -> f64 {
+        self.all_activities()
+            .map(|a| a.elevation_gain_meters())
+            .sum()
+    }
+
+    /// Elevation loss in meters of all activities, including the ones of multi-sport sessions.
+    pub fn total_elevation_loss_meters(&self) -> f64 {
+        self.all_activities()
+            .map(|a| a.elevation_loss_meters())
+            .sum()
+    }
+
+    /// Activity covering the longest distance, including the ones of multi-sport sessions.
+    pub fn longest_by_distance(&self) -> Option<&Activity> {
+        self.max_activity_by(Activity::total_distance_meters)
+    }
+
+    /// Activity lasting the longest, including the ones of multi-sport sessions.
+    pub fn longest_by_duration(&self) -> Option<&Activity> {
+        self.max_activity_by(Activity::total_time_seconds)
+    }
+
+    /// Activity with the biggest elevation gain, including the ones of multi-sport sessions.
+    pub fn with_most_elevation_gain(&self) -> Option<&Activity> {
+        self.max_activity_by(Activity::elevation_gain_meters)
+    }
+
+    fn max_activity_by(&self, value: impl Fn(&Activity) -> f64) -> Option<&Activity> {
+        self.all_activities()
+            .max_by(|a, b| value(a).total_cmp(&value(b)))
+    }
+}
+
+/// Gain and loss of the altitude over the track points with altitude.
+fn elevation_changes<'a>(track_points: impl Iterator<Item = &'a TrackPoint>) -> (f64, f64) {
+    let altitudes: Vec<f64> = track_points.filter_map(|tp| tp.altitude_meters).collect();
+    altitudes
+        .windows(2)
+        .fold((0.0, 0.0), |(gain, loss), w| match w[1] - w[0] {
+            d if d > 0.0 => (gain + d, loss),
+            d => (gain, loss - d),
+        })
+}
+
+const KILOJOULES_PER_KILOCALORIE: f64 = 4.184;
+
+/// Seconds passed from one track point to another, `None` if either has no time.
+pub(crate) fn seconds_between(from: &TrackPoint, to: &TrackPoint) -> Option<f64> {
+    let (from, to) = (from.time?, to.time?);
+    Some((to - from).num_milliseconds() as f64 / 1000.0)
+}
+
+/// `(seconds since the activity start, value)` of the track points having the value and time.
+fn timed_series(
+    activity: &Activity,
+    value: impl Fn(&TrackPoint) -> Option<f64>,
+) -> Vec<(f64, f64)> {
+    activity
+        .track_points()
+        .filter_map(|tp| {
+            let seconds = (tp.time? - activity.id).num_milliseconds() as f64 / 1000.0;
+            value(tp).map(|v| (seconds, v))
+        })
+        .collect()
+}
+
+/// Horizontal distance in meters covered between two track points.
+pub(crate) fn horizontal_distance(from: &TrackPoint, to: &TrackPoint) -> Option<f64> {
+    match (from.distance_meters, to.distance_meters) {
+        (Some(d1), Some(d2)) => Some(d2 - d1),
+        _ => match (&from.position, &to.position) {
+            (Some(p1), Some(p2)) => Some(p1.distance_to(p2)),
+            _ => None,
+        },
+    }
+}
+
+fn grade(from: &TrackPoint, to: &TrackPoint) -> Option<f64> {
+    let rise = to.altitude_meters? - from.altitude_meters?;
+    let run = horizontal_distance(from, to)?;
+    if run == 0.0 {
+        return None;
+    }
+    Some(rise / run * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+
+    fn track_point(distance: Option<f64>, altitude: Option<f64>) -> TrackPoint {
+        TrackPoint {
+            distance_meters: distance,
+            altitude_meters: altitude,
+            ..TrackPoint::default()
+        }
+    }
+
+    fn activity(track_points: Vec<TrackPoint>) -> Activity {
+        Activity {
+            laps: vec![ActivityLap {
+                track_points,
+                ..ActivityLap::default()
+            }],
+            ..Activity::default()
+        }
+    }
+
+    #[test]
+    fn grade_series_test() {
+        let activity = activity(vec![
+            track_point(Some(0.0), Some(100.0)),
+            track_point(Some(100.0), Some(110.0)),
+            track_point(Some(100.0), Some(120.0)),
+            track_point(Some(150.0), None),
+        ]);
+        assert_eq!(vec![Some(10.0), None, None], activity.grade_series());
+    }
+
+    #[test]
+    fn grade_series_position_fallback_test() {
+        let mut first = track_point(None, Some(0.0));
+        first.position = Some(Position {
+            latitude_degrees: 51.752415,
+            longitude_degrees: 39.18763,
+        });
+        let mut second = track_point(None, Some(10.0));
+        second.position = Some(Position {
+            latitude_degrees: 51.761415,
+            longitude_degrees: 39.18763,
+        });
+        let grades = activity(vec![first, second]).grade_series();
+        assert!((grades[0].unwrap() - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn activity_list_totals_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        assert_eq!(10, activity.laps.len());
+        assert_eq!(
+            activity.total_distance_meters(),
+            list.total_distance_meters()
+        );
+        assert_eq!(activity.total_time_seconds(), list.total_time_seconds());
+
+        let lap = ActivityLap {
+            distance_meters: 1500.0,
+            total_time_seconds: 600.0,
+            ..ActivityLap::default()
+        };
+        let expected_distance = list.total_distance_meters() + 1500.0;
+        list.multi_sport_sessions.push(MultiSportSession {
+            id: None,
+            sports: Some(vec![MultiActivity {
+                transition: None,
+                activity: Some(Activity {
+                    laps: vec![lap],
+                    ..Activity::default()
+                }),
+            }]),
+            notes: None,
+        });
+        assert_eq!(expected_distance, list.total_distance_meters());
+    }
+
+    #[test]
+    fn elapsed_time_seconds_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        let elapsed = activity.elapsed_time_seconds().unwrap();
+        assert_eq!(4505.652, elapsed);
+        assert!(elapsed >= activity.total_time_seconds());
+        // the laps sum up to 4400 seconds, the rest are pauses
+        assert!((elapsed - activity.total_time_seconds()) / elapsed < 0.05);
+
+        assert_eq!(None, Activity::default().elapsed_time_seconds());
+    }
+
+    #[test]
+    fn outlier_indices_test() {
+        let start = Utc::now();
+        let point = |seconds: i64, latitude_degrees: f64| TrackPoint {
+            time: Some(start + chrono::Duration::seconds(seconds)),
+            position: Some(Position {
+                latitude_degrees,
+                longitude_degrees: 39.18763,
+            }),
+            ..TrackPoint::default()
+        };
+        // 0.0001 degrees of latitude are roughly 11 meters
+        let mut activity = activity(vec![point(0, 51.7524), point(1, 51.7525)]);
+        activity.laps.push(ActivityLap {
+            track_points: vec![
+                point(2, 51.7526),
+                point(3, 51.9),
+                TrackPoint::default(),
+                point(4, 51.7528),
+                point(5, 51.7529),
+            ],
+            ..ActivityLap::default()
+        });
+        assert_eq!(vec![(1, 1)], activity.outlier_indices(20.0));
+        assert!(activity.outlier_indices(100_000.0).is_empty());
+    }
+
+    #[test]
+    fn highlights_test() {
+        let mut long = activity(vec![
+            track_point(Some(0.0), Some(100.0)),
+            track_point(Some(5000.0), Some(110.0)),
+        ]);
+        long.laps[0].distance_meters = 5000.0;
+        long.laps[0].total_time_seconds = 1500.0;
+        let mut hilly = activity(vec![
+            track_point(Some(0.0), Some(100.0)),
+            track_point(Some(3000.0), Some(300.0)),
+        ]);
+        hilly.laps[0].distance_meters = 3000.0;
+        hilly.laps[0].total_time_seconds = 1800.0;
+        let list = ActivityList {
+            activities: vec![long.clone(), hilly.clone()],
+            multi_sport_sessions: vec![],
+        };
+        assert_eq!(Some(&long), list.longest_by_distance());
+        assert_eq!(Some(&hilly), list.longest_by_duration());
+        assert_eq!(Some(&hilly), list.with_most_elevation_gain());
+        assert_eq!(None, ActivityList::default().longest_by_distance());
+    }
+
+    #[test]
+    fn best_power_test() {
+        let start = Utc.with_ymd_and_hms(2021, 5, 2, 8, 0, 0).unwrap();
+        let track_points = [200, 250, 400, 300, 100, 150]
+            .iter()
+            .enumerate()
+            .map(|(i, &watts)| TrackPoint {
+                time: Some(start + Duration::seconds(i as i64)),
+                extension: Some(ActivityTrackPointExtension {
+                    watts: Some(watts),
+                    ..ActivityTrackPointExtension::default()
+                }),
+                ..TrackPoint::default()
+            })
+            .collect();
+        let mut activity = activity(track_points);
+        activity.id = start;
+        assert_eq!(Some(400.0), activity.best_power(1.0));
+        assert_eq!(Some(350.0), activity.best_power(2.0));
+        assert_eq!(Some(287.5), activity.best_power(4.0));
+        assert_eq!(Some(250.0), activity.best_power(5.0));
+        assert_eq!(None, activity.best_power(6.0));
+    }
+
+    #[test]
+    fn estimated_calories_from_power_test() {
+        let start = Utc.with_ymd_and_hms(2021, 5, 2, 8, 0, 0).unwrap();
+        let track_points = (0..=3600)
+            .step_by(10)
+            .map(|seconds| TrackPoint {
+                time: Some(start + Duration::seconds(seconds)),
+                extension: Some(ActivityTrackPointExtension {
+                    watts: Some(100),
+                    ..ActivityTrackPointExtension::default()
+                }),
+                ..TrackPoint::default()
+            })
+            .collect();
+        let mut activity = activity(track_points);
+        activity.id = start;
+        // 360 kJ of work at 24% efficiency
+        assert_eq!(Some(359), activity.estimated_calories_from_power(0.24));
+        assert_eq!(None, activity.estimated_calories_from_power(0.0));
+        assert_eq!(
+            None,
+            Activity::default().estimated_calories_from_power(0.24)
+        );
+    }
+
+    #[test]
+    fn trimp_test() {
+        let start = Utc.with_ymd_and_hms(2021, 5, 2, 8, 0, 0).unwrap();
+        let track_points = (0..=3600)
+            .step_by(10)
+            .map(|seconds| TrackPoint {
+                time: Some(start + Duration::seconds(seconds)),
+                heart_rate_bpm: Some(150),
+                ..TrackPoint::default()
+            })
+            .collect();
+        let mut activity = activity(track_points);
+        activity.id = start;
+        // an hour at 90 / 130 of the heart rate reserve
+        let male = activity.trimp(60, 190, true).unwrap();
+        assert!((male - 100.44).abs() < 0.01);
+        let female = activity.trimp(60, 190, false).unwrap();
+        assert!((female - 113.52).abs() < 0.01);
+        assert_eq!(None, activity.trimp(190, 60, true));
+        assert_eq!(None, Activity::default().trimp(60, 190, true));
+    }
+
+    #[test]
+    fn best_pace_test() {
+        let start = Utc.with_ymd_and_hms(2021, 5, 2, 8, 0, 0).unwrap();
+        let track_points = [0.0, 200.0, 500.0, 700.0, 1000.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &distance)| TrackPoint {
+                time: Some(start + Duration::seconds(60 * i as i64)),
+                distance_meters: Some(distance),
+                ..TrackPoint::default()
+            })
+            .collect();
+        let mut activity = activity(track_points);
+        activity.id = start;
+        assert_eq!(Some(40.0), activity.best_pace(200.0));
+        assert_eq!(Some(60.0), activity.best_pace(300.0));
+        assert_eq!(Some(240.0), activity.best_pace(1000.0));
+        assert_eq!(None, activity.best_pace(1000.5));
+    }
+
+    #[test]
+    fn effective_max_speed_test() {
+        let speed_point = |speed| TrackPoint {
+            extension: Some(ActivityTrackPointExtension {
+                speed: Some(speed),
+                ..ActivityTrackPointExtension::default()
+            }),
+            ..TrackPoint::default()
+        };
+        let mut lap = ActivityLap {
+            track_points: vec![speed_point(4.5), TrackPoint::default(), speed_point(5.5)],
+            ..ActivityLap::default()
+        };
+        assert_eq!(Some(5.5), lap.effective_max_speed());
+        lap.extension = Some(ActivityLapExtension {
+            max_speed: Some(6.0),
+            ..ActivityLapExtension::default()
+        });
+        assert_eq!(Some(6.0), lap.effective_max_speed());
+        lap.maximum_speed = Some(6.5);
+        assert_eq!(Some(6.5), lap.effective_max_speed());
+        assert_eq!(None, ActivityLap::default().effective_max_speed());
+    }
+
+    #[test]
+    fn lap_totals_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let totals: LapTotals = list.activities[0].laps.iter().collect();
+        assert_eq!(10, totals.laps);
+        assert_eq!(4400.0, totals.total_time_seconds);
+        assert!((totals.distance_meters - 9144.7001953125).abs() < 1e-6);
+        assert_eq!(779, totals.calories);
+        assert_eq!(Some(3.0805555979410806), totals.maximum_speed);
+        assert_eq!(Some(156), totals.maximum_heart_rate_bpm);
+        assert_eq!(
+            LapTotals::default(),
+            Vec::<ActivityLap>::new().iter().collect()
+        );
+    }
+
+    #[test]
+    fn track_points_with_context_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let mut biking = activity(vec![track_point(None, None), track_point(None, None)]);
+        biking.sport = Sport::Biking;
+        list.activities.push(biking);
+        let total: usize = list
+            .activities
+            .iter()
+            .map(|a| a.track_points().count())
+            .sum();
+        assert_eq!(total, list.track_points_with_context().count());
+        let biking_points: Vec<_> = list
+            .track_points_with_context()
+            .filter(|(a, _, _)| a.sport == Sport::Biking)
+            .collect();
+        assert_eq!(2, biking_points.len());
+        assert!(biking_points
+            .iter()
+            .all(|(a, lap, tp)| std::ptr::eq(*lap, &a.laps[0]) && lap.track_points.contains(tp)));
+        assert_eq!(
+            list.activities[0].sport,
+            list.track_points_with_context().next().unwrap().0.sport
+        );
+    }
+
+    #[test]
+    fn samples_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        let samples = activity.samples();
+        assert_eq!(activity.track_points().count(), samples.len());
+        for (tp, sample) in activity.track_points().zip(&samples) {
+            assert_eq!(tp.time, sample.time);
+            assert_eq!(tp.altitude_meters, sample.altitude_meters);
+            assert_eq!(tp.heart_rate_bpm, sample.heart_rate_bpm);
+        }
+
+        let tp = TrackPoint {
+            position: Some(Position {
+                latitude_degrees: 51.752415,
+                longitude_degrees: 39.18763,
+            }),
+            distance_meters: Some(12.5),
+            extension: Some(ActivityTrackPointExtension {
+                speed: Some(3.1),
+                run_cadence: Some(88),
+                watts: Some(250),
+                ..ActivityTrackPointExtension::default()
+            }),
+            ..TrackPoint::default()
+        };
+        let sample = Sample::from(&tp);
+        assert_eq!(Some(51.752415), sample.latitude_degrees);
+        assert_eq!(Some(39.18763), sample.longitude_degrees);
+        assert_eq!(Some(12.5), sample.distance_meters);
+        assert_eq!(Some(88), sample.cadence);
+        assert_eq!(Some(3.1), sample.speed);
+        assert_eq!(Some(250), sample.watts);
+        assert_eq!(None, sample.altitude_meters);
+    }
+
+    #[test]
+    fn heart_rate_histogram_test() {
+        let start = Utc.with_ymd_and_hms(2021, 5, 2, 8, 0, 0).unwrap();
+        let track_points = [
+            (0, Some(118)),
+            (10, Some(125)),
+            (15, None),
+            (30, Some(139)),
+            (35, Some(141)),
+            (40, Some(150)),
+        ]
+        .iter()
+        .map(|&(seconds, hr)| TrackPoint {
+            time: Some(start + Duration::seconds(seconds)),
+            heart_rate_bpm: hr,
+            ..TrackPoint::default()
+        })
+        .collect();
+        let activity = activity(track_points);
+        let histogram = activity.heart_rate_histogram(10);
+        let expected: BTreeMap<u8, f64> = vec![(110, 10.0), (120, 20.0), (130, 5.0), (140, 5.0)]
+            .into_iter()
+            .collect();
+        assert_eq!(expected, histogram);
+        assert!(activity.heart_rate_histogram(0).is_empty());
+    }
+
+    #[test]
+    fn average_speed_from_points_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let lap = &list.activities[0].laps[0];
+        let recorded = lap.distance_meters / lap.total_time_seconds;
+        let computed = lap.average_speed_from_points().unwrap();
+        assert!((computed - recorded).abs() / recorded < 0.05);
+        assert_eq!(None, ActivityLap::default().average_speed_from_points());
+    }
+
+    #[test]
+    fn elevation_gain_test() {
+        let mut activity = activity(vec![
+            track_point(None, Some(100.0)),
+            track_point(None, Some(110.0)),
+            track_point(None, None),
+            track_point(None, Some(105.0)),
+        ]);
+        activity.laps.push(ActivityLap {
+            track_points: vec![track_point(None, Some(120.0))],
+            ..ActivityLap::default()
+        });
+        assert_eq!(10.0, activity.laps[0].elevation_gain_meters());
+        assert_eq!(5.0, activity.laps[0].elevation_loss_meters());
+        assert_eq!(25.0, activity.elevation_gain_meters());
+        assert_eq!(5.0, activity.elevation_loss_meters());
+
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        assert!(list.total_elevation_gain_meters() > 0.0);
+        assert_eq!(
+            list.activities[0].elevation_gain_meters(),
+            list.total_elevation_gain_meters()
+        );
+        assert_eq!(
+            list.activities[0].elevation_loss_meters(),
+            list.total_elevation_loss_meters()
+        );
+    }
+
+    #[test]
+    fn hr_recovery_test() {
+        let start = Utc::now();
+        let heart_rates = [140, 165, 178, 175, 160, 150, 138, 130];
+        let track_points = heart_rates
+            .iter()
+            .enumerate()
+            .map(|(i, &hr)| TrackPoint {
+                time: Some(start + chrono::Duration::seconds(i as i64 * 30)),
+                heart_rate_bpm: Some(hr),
+                ..TrackPoint::default()
+            })
+            .collect();
+        let activity = activity(track_points);
+        assert_eq!(Some(40), activity.hr_recovery(120.0));
+        assert_eq!(Some(3), activity.hr_recovery(20.0));
+        assert_eq!(Some(48), activity.hr_recovery(150.0));
+        assert_eq!(None, activity.hr_recovery(180.0));
+    }
+
+    #[test]
+    fn lap_calories_rates_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let lap = &list.activities[0].laps[0];
+        assert_eq!(Some(779.0), lap.calories_per_km());
+        assert!((lap.calories_per_hour().unwrap() - 5341.714).abs() < 0.001);
+
+        let lap = ActivityLap::default();
+        assert_eq!(None, lap.calories_per_km());
+        assert_eq!(None, lap.calories_per_hour());
+    }
+
+    #[test]
+    fn lap_splits_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        let splits = activity.lap_splits();
+        assert_eq!(10, splits.len());
+        assert!(splits
+            .windows(2)
+            .all(|w| w[0].cumulative_distance_meters < w[1].cumulative_distance_meters));
+        assert_eq!(
+            LapSplit {
+                lap_index: 0,
+                cumulative_distance_meters: 1000.0,
+                cumulative_time_seconds: 525.0,
+                distance_meters: 1000.0,
+                time_seconds: 525.0,
+                pace_seconds_per_km: Some(525.0),
+            },
+            splits[0]
+        );
+        let last = splits.last().unwrap();
+        assert_eq!(
+            activity.total_distance_meters(),
+            last.cumulative_distance_meters
+        );
+        assert_eq!(activity.total_time_seconds(), last.cumulative_time_seconds);
+    }
+
+    #[test]
+    fn assert_monotonic_time_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let mut activity = list.activities[0].clone();
+        assert_eq!(Ok(()), activity.assert_monotonic_time());
+
+        activity.laps[2].track_points[5].time = Some(activity.id);
+        assert_eq!(Err(vec![(2, 5)]), activity.assert_monotonic_time());
+    }
+
+    #[test]
+    fn average_cadence_test() {
+        let cadence_point = |cadence: Option<u8>, run_cadence: Option<u8>| TrackPoint {
+            cadence,
+            extension: Some(ActivityTrackPointExtension {
+                run_cadence,
+                ..ActivityTrackPointExtension::default()
+            }),
+            ..TrackPoint::default()
+        };
+        let activity = activity(vec![
+            cadence_point(Some(80), None),
+            cadence_point(None, Some(90)),
+            cadence_point(None, None),
+            cadence_point(Some(85), Some(10)),
+        ]);
+        assert_eq!(Some(85.0), activity.average_cadence());
+        assert_eq!(Some(90), activity.max_cadence());
+        assert_eq!(None, Activity::default().average_cadence());
+        assert_eq!(None, Activity::default().max_cadence());
+
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        let average = activity.average_cadence().unwrap();
+        assert!(average > 0.0 && average <= f64::from(activity.max_cadence().unwrap()));
+    }
+
+    #[test]
+    fn segment_metrics_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        let points: Vec<&TrackPoint> = activity.track_points().collect();
+        let (first, last) = (
+            points[0].time.unwrap(),
+            points[points.len() - 1].time.unwrap(),
+        );
+        let full = activity.segment_metrics(first - chrono::Duration::hours(1), last);
+        assert_eq!(
+            (last - first).num_milliseconds() as f64 / 1000.0,
+            full.elapsed_time_seconds
+        );
+
+        let (start, end) = (points[1000].time.unwrap(), points[2000].time.unwrap());
+        let segment = activity.segment_metrics(start, end);
+        assert_eq!(
+            (end - start).num_milliseconds() as f64 / 1000.0,
+            segment.elapsed_time_seconds
+        );
+        assert!(segment.distance_meters > 0.0);
+        assert!(segment.distance_meters < full.distance_meters);
+        assert!(segment.elapsed_time_seconds < full.elapsed_time_seconds);
+        assert!(segment.elevation_gain_meters <= full.elevation_gain_meters);
+        let speed = segment.average_speed_mps.unwrap();
+        assert!((speed - segment.distance_meters / segment.elapsed_time_seconds).abs() < 1e-9);
+        let hr = segment.average_heart_rate_bpm.unwrap();
+        assert!(hr > 40.0 && hr < 220.0);
+
+        assert_eq!(
+            SegmentMetrics::default(),
+            activity.segment_metrics(last + chrono::Duration::seconds(1), last)
+        );
+    }
+
+    #[test]
+    fn sensor_present_fraction_test() {
+        let states = [
+            Some(SensorState::Present),
+            Some(SensorState::Absent),
+            None,
+            Some(SensorState::Present),
+            Some(SensorState::Present),
+        ];
+        let mut lap = ActivityLap {
+            track_points: states
+                .iter()
+                .map(|state| TrackPoint {
+                    sensor_state: state.clone(),
+                    ..TrackPoint::default()
+                })
+                .collect(),
+            ..ActivityLap::default()
+        };
+        assert_eq!(Some(0.75), lap.sensor_present_fraction());
+        for tp in &mut lap.track_points {
+            tp.sensor_state = None;
+        }
+        assert_eq!(None, lap.sensor_present_fraction());
+    }
+}
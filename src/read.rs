@@ -1,11 +1,13 @@
 use std::io::BufRead;
 use std::num::{ParseFloatError, ParseIntError};
+use std::ops::BitOr;
 use std::str::{FromStr, ParseBoolError};
 
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use quick_xml::events::attributes::AttrError;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::reader::Reader;
+use quick_xml::Writer;
 use thiserror::Error;
 
 use crate::types::*;
@@ -28,71 +30,316 @@ pub enum ReadError {
     ParseDateError(#[from] chrono::ParseError),
     #[error("error parsing attribute '{0}'")]
     XmlAttrError(#[from] AttrError),
+    #[error("unknown type '{0}'")]
+    UnknownType(String),
+    #[error("unexpected end of file")]
+    UnexpectedEof,
+    #[error("required element '{0}' is missing")]
+    MissingElement(&'static str),
+    #[error("required attribute '{0}' is missing")]
+    MissingAttribute(&'static str),
+    #[error("invalid value '{1}' of element '{0}'")]
+    InvalidValue(&'static str, String),
 }
 
 macro_rules! opt_read_value_as {
-    ($to: tt. $attr:tt, $r: tt, $b: tt, $ft:ty) => {{
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ft:ty, $ctx: tt) => {{
         loop {
-            match $r.read_event_into(&mut $b) {
-                Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                    b"Value" => opt_read_text_as!($to.$attr, $r, $b, $ft),
-                    _ => (),
-                },
-                Ok(Event::End(ref e)) => {
-                    if e.name().into_inner() == b"Value" {
-                        break;
+            match next_event($r, &mut $b, $ctx) {
+                Ok(Event::Start(ref e)) if e.name().into_inner() == b"Value" => {
+                    // an empty value is the same as a missing one
+                    if let Some(t) = read_text($r, &mut $b, $ctx)? {
+                        match numeric_value(&t) {
+                            "" => (),
+                            v => $to.$attr = Some(<$ft>::from_str(v)?),
+                        }
                     }
+                    break;
                 }
+                Ok(Event::End(_)) => break,
+                Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
                 Err(e) => return Err(ReadError::XmlReadError(e)),
-                _ => (),
+                _ => $ctx.unrecognized(),
             }
         }
     }};
 }
 
 macro_rules! must_read_text_as {
-    ($to: tt. $attr:tt, $r: tt, $b: tt, $ft: ty) => {{
-        if let Ok(Event::Text(ref t)) = $r.read_event_into(&mut $b) {
-            $to.$attr = <$ft>::from_str(&t.unescape()?.into_owned())?;
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ft: ty, $ctx: tt) => {{
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = <$ft>::from_str(&t)?;
         }
     }};
 }
 
 macro_rules! opt_read_text_as {
-    ($to: tt. $attr:tt, $r: tt, $b: tt, $ft: ty) => {{
-        if let Ok(Event::Text(ref t)) = $r.read_event_into(&mut $b) {
-            $to.$attr = Some(<$ft>::from_str(&t.unescape()?.into_owned())?);
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ft: ty, $ctx: tt) => {{
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = Some(<$ft>::from_str(&t)?);
         }
     }};
 }
 
 macro_rules! must_read_text {
-    ($to: tt. $attr:tt, $r: tt, $b: tt) => {{
-        if let Ok(Event::Text(ref t)) = $r.read_event_into(&mut $b) {
-            $to.$attr = t.unescape()?.into_owned();
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ctx: tt) => {{
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = t;
         }
     }};
 }
 
 macro_rules! opt_read_text {
-    ($to: tt. $attr:tt, $r: tt, $b: tt) => {{
-        if let Ok(Event::Text(ref t)) = $r.read_event_into(&mut $b) {
-            $to.$attr = Some(t.unescape()?.into_owned());
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ctx: tt) => {{
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = Some(t);
+        }
+    }};
+}
+
+macro_rules! opt_read_cadence {
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ctx: tt) => {{
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = Some(parse_cadence(&t, $ctx)?);
+        }
+    }};
+}
+
+macro_rules! opt_read_altitude {
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ctx: tt) => {{
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = Some(parse_altitude(&t, $ctx)?);
         }
     }};
 }
 
 macro_rules! must_read_text_as_date {
-    ($to: tt. $attr:tt, $r: tt, $b: tt) => {
-        if let Ok(Event::Text(ref t)) = $r.read_event_into(&mut $b) {
-            $to.$attr =
-                chrono::DateTime::parse_from_rfc3339(t.unescape()?.into_owned().as_str())?.into();
+    ($to: tt. $attr:tt, $r: tt, $b: tt, $ctx: tt) => {
+        if let Some(t) = read_text($r, &mut $b, $ctx)? {
+            $to.$attr = parse_time(&t, $ctx)?;
         }
     };
 }
 
+/// Options controlling how TCX data is read.
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// Skip the content of `<Track>` elements, so laps are read with empty `track_points`.
+    /// Useful to save memory when only the metadata of huge files is needed.
+    pub skip_track_points: bool,
+    /// Fix up values violating the schema which are read as they are or fail the read
+    /// otherwise, e.g. cadence above 254 is clamped to 254 and a three-letter `LangID` is
+    /// shortened to its two-letter code.
+    pub lenient: bool,
+    /// Fail on missing or empty required elements and on invalid values which are read with
+    /// a default or as they are otherwise, e.g. a lap without `Intensity` is `Active`, a lap
+    /// without `StartTime` starts at its first track point and an empty `UnitId` is `None`.
+    pub strict: bool,
+    /// Track point fields to read, the content of other fields is skipped.
+    pub trackpoint_fields: TrackPointFields,
+    /// Treat altitude values as feet and convert them to meters. Not allowed by the schema,
+    /// but some applications export altitude in feet.
+    pub altitude_feet: bool,
+    /// Record the elements read from the document in their original order into
+    /// [`ReadStats::element_events`].
+    pub record_element_events: bool,
+    /// Keep the comments of the document in [`TrainingCenterDatabase::comments`].
+    pub record_comments: bool,
+    /// `chrono` formats tried in order for timestamps that aren't valid RFC 3339, e.g.
+    /// `2020-12-28 13:36:16Z`. Timestamps parsed without a time zone are taken as UTC.
+    pub timestamp_formats: Vec<String>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            skip_track_points: false,
+            lenient: false,
+            strict: false,
+            trackpoint_fields: TrackPointFields::default(),
+            altitude_feet: false,
+            record_element_events: false,
+            record_comments: false,
+            timestamp_formats: vec![
+                String::from("%Y-%m-%dT%H:%M:%S%.f"),
+                String::from("%Y-%m-%d %H:%M:%S%.f%:z"),
+                String::from("%Y-%m-%d %H:%M:%S%.fZ"),
+                String::from("%Y-%m-%d %H:%M:%S%.f"),
+            ],
+        }
+    }
+}
+
+/// Set of the optional track point fields to read, the time is always read.
+///
+/// Flags are combined with `|`, e.g. `TrackPointFields::POSITION | TrackPointFields::ALTITUDE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackPointFields(u8);
+
+impl TrackPointFields {
+    pub const NONE: Self = Self(0);
+    pub const POSITION: Self = Self(1);
+    pub const ALTITUDE: Self = Self(1 << 1);
+    pub const DISTANCE: Self = Self(1 << 2);
+    pub const HEART_RATE: Self = Self(1 << 3);
+    pub const CADENCE: Self = Self(1 << 4);
+    pub const SENSOR_STATE: Self = Self(1 << 5);
+    pub const EXTENSIONS: Self = Self(1 << 6);
+    pub const ALL: Self = Self((1 << 7) - 1);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether the track point child element with `name` has to be read.
+    fn allows(self, name: &[u8]) -> bool {
+        match name {
+            b"Position" => self.contains(Self::POSITION),
+            b"AltitudeMeters" => self.contains(Self::ALTITUDE),
+            b"DistanceMeters" => self.contains(Self::DISTANCE),
+            b"HeartRateBpm" => self.contains(Self::HEART_RATE),
+            b"Cadence" => self.contains(Self::CADENCE),
+            b"SensorState" => self.contains(Self::SENSOR_STATE),
+            b"Extensions" => self.contains(Self::EXTENSIONS),
+            _ => true,
+        }
+    }
+}
+
+impl Default for TrackPointFields {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl BitOr for TrackPointFields {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Counts of the data read from TCX.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadStats {
+    pub activities: usize,
+    pub laps: usize,
+    pub track_points: usize,
+    /// Number of track points of every read lap, in the read order.
+    pub lap_track_points: Vec<usize>,
+    pub track_points_with_position: usize,
+    pub track_points_with_heart_rate: usize,
+    /// Values fixed up instead of failing the read.
+    pub coercions: Coercions,
+    /// Elements read from the document in the original order, only recorded with
+    /// [`ReadOptions::record_element_events`].
+    pub element_events: Vec<ElementEvent>,
+}
+
+/// Element of the read document, see [`ReadStats::element_events`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementEvent {
+    pub name: String,
+    /// Nesting level of the element, the root element is at 0.
+    pub depth: usize,
+}
+
+/// Counts of the values violating the schema which were fixed up instead of failing the read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coercions {
+    /// Cadence values above 254 clamped to 254.
+    pub cadence_clamped: usize,
+    /// Distances given in kilometers with a `unit="km"` attribute converted to meters.
+    pub distance_unit_converted: usize,
+    /// Empty required integers, e.g. `<UnitId/>`, left at 0.
+    pub empty_values_defaulted: usize,
+    /// Three-letter ISO 639-2 `LangID`s, e.g. `ENG`, replaced with their two-letter code.
+    pub lang_id_shortened: usize,
+    /// Laps without `DistanceMeters` given the distance covered by their track points.
+    pub lap_distance_backfilled: usize,
+}
+
+/// State shared by the read functions during a single read.
+#[derive(Debug, Default)]
+pub struct ReadContext {
+    pub options: ReadOptions,
+    pub stats: ReadStats,
+    /// Nesting level of the next element.
+    depth: usize,
+    /// Element just started, recorded unless the read function doesn't recognize it.
+    pending_event: Option<ElementEvent>,
+    comments: Vec<(usize, String)>,
+}
+
+impl ReadContext {
+    pub fn new(options: ReadOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Leaves the element just started out of the recorded events, it isn't read.
+    fn unrecognized(&mut self) {
+        self.pending_event = None;
+    }
+}
+
+/// Reads the next event, recording the elements and comments along the way if asked to.
+fn next_event<'b, B: BufRead>(
+    reader: &mut Reader<B>,
+    buf: &'b mut Vec<u8>,
+    ctx: &mut ReadContext,
+) -> quick_xml::Result<Event<'b>> {
+    if let Some(event) = ctx.pending_event.take() {
+        ctx.stats.element_events.push(event);
+    }
+    let position = reader.buffer_position();
+    let event = reader.read_event_into(buf)?;
+    match event {
+        Event::Start(ref e) | Event::Empty(ref e) => {
+            if ctx.options.record_element_events {
+                ctx.pending_event = Some(ElementEvent {
+                    name: reader.decoder().decode(e.name().into_inner())?.into_owned(),
+                    depth: ctx.depth,
+                });
+            }
+            if let Event::Start(_) = event {
+                ctx.depth += 1;
+            }
+        }
+        Event::End(_) => ctx.depth = ctx.depth.saturating_sub(1),
+        Event::Comment(ref e) if ctx.options.record_comments => ctx
+            .comments
+            .push((position, reader.decoder().decode(e)?.into_owned())),
+        _ => (),
+    }
+    Ok(event)
+}
+
+/// Skips the content of the element just started up to its end.
+fn skip_element<B: BufRead>(
+    reader: &mut Reader<B>,
+    ctx: &mut ReadContext,
+) -> Result<(), ReadError> {
+    let depth = ctx.depth;
+    let mut buf = Vec::new();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::End(_)) if ctx.depth < depth => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
 pub fn read_training_center<B: BufRead>(
     reader: &mut Reader<B>,
+    ctx: &mut ReadContext,
 ) -> Result<TrainingCenterDatabase, ReadError> {
     let mut buf = Vec::new();
     let mut tc_db = TrainingCenterDatabase {
@@ -101,824 +348,3611 @@ pub fn read_training_center<B: BufRead>(
         workout_list: None,
         course_list: None,
         author: None,
+        comments: Vec::new(),
     };
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Author" => {
-                    let e_type = read_type(e)?;
-                    if e_type.as_str() == "Application_t" {
-                        tc_db.author = Some(SourceType::Application(read_application(
-                            reader, b"Author",
-                        )?));
-                    } else if e_type.as_str() == "Device_t" {
-                        tc_db.author = Some(SourceType::Device(read_device(reader, b"Author")?));
-                    }
+                b"TrainingCenterDatabase" => (),
+                b"Author" => tc_db.author = read_source(reader, b"Author", e, ctx)?,
+                b"Folders" => tc_db.folders = Some(read_folders(reader, b"Folders", ctx)?),
+                b"Workouts" => {
+                    tc_db.workout_list = Some(read_workout_list(reader, b"Workouts", ctx)?)
                 }
+                b"Courses" => tc_db.course_list = Some(read_course_list(reader, b"Courses", ctx)?),
                 b"Activities" => {
-                    tc_db.activity_list = Some(read_activity_list(reader, b"Activities")?)
+                    // concatenated exports may have several activity lists, they're merged
+                    let list = read_activity_list(reader, b"Activities", ctx)?;
+                    let al = tc_db
+                        .activity_list
+                        .get_or_insert_with(ActivityList::default);
+                    al.activities.extend(list.activities);
+                    al.multi_sport_sessions.extend(list.multi_sport_sessions);
                 }
-                _ => {}
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => match e.name().into_inner() {
-                b"TrainingCenterDatabase" => break,
-                _ => {}
+            Ok(Event::Empty(ref e)) => match e.name().into_inner() {
+                b"Folders" => tc_db.folders = Some(Folders::default()),
+                b"Workouts" => tc_db.workout_list = Some(WorkoutList::default()),
+                b"Courses" => tc_db.course_list = Some(CourseList::default()),
+                b"Activities" => {
+                    tc_db
+                        .activity_list
+                        .get_or_insert_with(ActivityList::default);
+                }
+                _ => ctx.unrecognized(),
             },
+            Ok(Event::End(ref e)) if e.name().into_inner() == b"TrainingCenterDatabase" => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
     }
+    tc_db.comments = std::mem::take(&mut ctx.comments);
     Ok(tc_db)
 }
 
-fn read_type(e: &BytesStart) -> Result<String, ReadError> {
-    match e
-        .attributes()
-        .filter(|a| a.is_ok() && a.as_ref().unwrap().key.into_inner() == b"xsi:type")
-        .next()
-    {
-        None => Err(ReadError::TypeNotDefined),
-        Some(ar) => Ok(ar?.unescape_value()?.into_owned()),
+/// Reads the `AbstractSource_t` element by its `xsi:type`, the content of unknown types is skipped.
+/// A source without `xsi:type` is skipped too, unless reading strictly.
+fn read_source<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    e: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Option<SourceType>, ReadError> {
+    let source_type = match read_type(reader, e) {
+        Err(ReadError::TypeNotDefined) if !ctx.options.strict => String::new(),
+        r => r?,
+    };
+    match source_type.as_str() {
+        "Application_t" => Ok(Some(SourceType::Application(read_application(
+            reader, close_tag, ctx,
+        )?))),
+        "Device_t" => Ok(Some(SourceType::Device(read_device(
+            reader, close_tag, ctx,
+        )?))),
+        _ => {
+            skip_element(reader, ctx)?;
+            Ok(None)
+        }
     }
 }
 
-fn read_activity_list<B: BufRead>(
+/// Reads every root element of the stream, for several TCX documents written one after another.
+pub fn read_training_centers<B: BufRead>(
     reader: &mut Reader<B>,
-    close_tag: &[u8],
-) -> Result<ActivityList, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<Vec<TrainingCenterDatabase>, ReadError> {
     let mut buf = Vec::new();
-    let mut al = ActivityList::default();
+    let mut tc_dbs = Vec::new();
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Activity" => al.activities.push(read_activity(reader, b"Activity", e)?),
-                _ => (),
-            },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"TrainingCenterDatabase" => {
+                tc_dbs.push(read_training_center(reader, ctx)?)
+            }
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"TrainingCenterDatabase" => {
+                tc_dbs.push(TrainingCenterDatabase::default())
             }
+            Ok(Event::Eof) => break,
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(al)
+    Ok(tc_dbs)
 }
 
-fn read_activity<B: BufRead>(
-    reader: &mut Reader<B>,
-    close_tag: &[u8],
-    activity_element: &BytesStart,
-) -> Result<Activity, ReadError> {
-    let mut buf = Vec::new();
-    let mut activity = Activity::default();
-    for ar in activity_element.attributes() {
-        if let Ok(a) = ar {
-            match a.key.into_inner() {
-                b"Sport" => activity.sport = Sport::from_str(&a.unescape_value()?.to_owned())?,
-                _ => (),
+/// Factor converting the value of the distance element to meters. Some producers write
+/// kilometers with a non-schema `unit="km"` attribute, which is only honored in lenient mode.
+fn distance_unit_factor<B: BufRead>(
+    reader: &Reader<B>,
+    e: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<f64, ReadError> {
+    if !ctx.options.lenient {
+        return Ok(1.0);
+    }
+    for a in e.attributes().flatten() {
+        if a.key.into_inner() == b"unit" && a.decode_and_unescape_value(reader)? == "km" {
+            ctx.stats.coercions.distance_unit_converted += 1;
+            return Ok(1000.0);
+        }
+    }
+    Ok(1.0)
+}
+
+/// Strips the padding and the leading `+` some producers add to numeric `<Value>`s.
+fn numeric_value(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('+').unwrap_or(s)
+}
+
+/// Maximum cadence allowed by the schema.
+const MAX_CADENCE: u8 = 254;
+
+const METERS_PER_FOOT: f64 = 0.3048;
+
+fn parse_cadence(s: &str, ctx: &mut ReadContext) -> Result<u8, ReadError> {
+    let clamped = match u8::from_str(s) {
+        Ok(cadence) if cadence > MAX_CADENCE && ctx.options.lenient => MAX_CADENCE,
+        Ok(cadence) => return Ok(cadence),
+        Err(_) if ctx.options.lenient && u64::from_str(s).is_ok() => MAX_CADENCE,
+        Err(e) => return Err(ReadError::ParseIntError(e)),
+    };
+    ctx.stats.coercions.cadence_clamped += 1;
+    Ok(clamped)
+}
+
+/// Parses a required integer of the `name` element. An empty value is an error in strict
+/// mode, otherwise it's counted in [`Coercions::empty_values_defaulted`] and `None` is returned.
+fn parse_required_int<T>(
+    name: &'static str,
+    text: Option<&str>,
+    ctx: &mut ReadContext,
+) -> Result<Option<T>, ReadError>
+where
+    T: FromStr<Err = ParseIntError>,
+{
+    match text.map(str::trim) {
+        Some(t) if !t.is_empty() => Ok(Some(T::from_str(t)?)),
+        _ if ctx.options.strict => Err(ReadError::InvalidValue(name, String::new())),
+        _ => {
+            ctx.stats.coercions.empty_values_defaulted += 1;
+            Ok(None)
+        }
+    }
+}
+
+/// ISO 639-2 codes of the languages with an ISO 639-1 code, both the bibliographic
+/// and the terminological variant where they differ.
+const LANG_ID_ALPHA2: &[(&str, &str)] = &[
+    ("ces", "cs"),
+    ("chi", "zh"),
+    ("cze", "cs"),
+    ("dan", "da"),
+    ("deu", "de"),
+    ("dut", "nl"),
+    ("ell", "el"),
+    ("eng", "en"),
+    ("fin", "fi"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("ger", "de"),
+    ("gre", "el"),
+    ("hun", "hu"),
+    ("ita", "it"),
+    ("jpn", "ja"),
+    ("kor", "ko"),
+    ("nld", "nl"),
+    ("nor", "no"),
+    ("pol", "pl"),
+    ("por", "pt"),
+    ("rus", "ru"),
+    ("spa", "es"),
+    ("swe", "sv"),
+    ("tur", "tr"),
+    ("ukr", "uk"),
+    ("zho", "zh"),
+];
+
+/// Checks the `LangID` is a two-letter code in strict mode, other values are kept as they are.
+/// Reading leniently, a known three-letter code is replaced with its two-letter one in the same
+/// case.
+fn parse_lang_id(s: String, ctx: &mut ReadContext) -> Result<String, ReadError> {
+    if s.chars().count() == 2 {
+        return Ok(s);
+    }
+    if ctx.options.strict {
+        return Err(ReadError::InvalidValue("LangID", s));
+    }
+    if !ctx.options.lenient {
+        return Ok(s);
+    }
+    let alpha2 = LANG_ID_ALPHA2
+        .iter()
+        .find(|(alpha3, _)| alpha3.eq_ignore_ascii_case(&s))
+        .map(|(_, alpha2)| alpha2);
+    match alpha2 {
+        Some(alpha2) => {
+            ctx.stats.coercions.lang_id_shortened += 1;
+            if s.chars().all(|c| c.is_ascii_uppercase()) {
+                Ok(alpha2.to_ascii_uppercase())
+            } else {
+                Ok(alpha2.to_string())
             }
         }
+        None => Ok(s),
+    }
+}
+
+/// Parses the altitude in meters of track points, course laps and course points.
+fn parse_altitude(s: &str, ctx: &ReadContext) -> Result<f64, ReadError> {
+    let altitude = f64::from_str(s)?;
+    if ctx.options.altitude_feet {
+        Ok(altitude * METERS_PER_FOOT)
+    } else {
+        Ok(altitude)
+    }
+}
+
+/// Parses a timestamp as RFC 3339, falling back to the [`ReadOptions::timestamp_formats`].
+/// The RFC 3339 error is returned if none of the formats matches.
+fn parse_time(s: &str, ctx: &ReadContext) -> Result<DateTime<Utc>, ReadError> {
+    let rfc3339_error = match DateTime::parse_from_rfc3339(s) {
+        Ok(time) => return Ok(time.into()),
+        Err(e) => e,
+    };
+    for format in &ctx.options.timestamp_formats {
+        if let Ok(time) = DateTime::parse_from_str(s, format) {
+            return Ok(time.into());
+        }
+        if let Ok(time) = NaiveDateTime::parse_from_str(s, format) {
+            return Ok(Utc.from_utc_datetime(&time));
+        }
+    }
+    Err(rfc3339_error.into())
+}
+
+fn read_type<B: BufRead>(reader: &Reader<B>, e: &BytesStart) -> Result<String, ReadError> {
+    match e
+        .attributes()
+        .find(|a| a.is_ok() && a.as_ref().unwrap().key.into_inner() == b"xsi:type")
+    {
+        None => Err(ReadError::TypeNotDefined),
+        Some(ar) => Ok(ar?.decode_and_unescape_value(reader)?.into_owned()),
+    }
+}
+
+/// Whether the heart rate element is of `HeartRateAsPercentOfMax_t` type rather than
+/// the usual, and not necessarily specified, `HeartRateInBeatsPerMinute_t`.
+fn is_percent_of_max<B: BufRead>(reader: &Reader<B>, e: &BytesStart) -> Result<bool, ReadError> {
+    for a in e.attributes().flatten() {
+        if a.key.into_inner() == b"xsi:type" {
+            return Ok(a.decode_and_unescape_value(reader)? == "HeartRateAsPercentOfMax_t");
+        }
     }
+    Ok(false)
+}
+
+/// Reads the content of a text-only element up to its end, `None` if the element is empty.
+/// Text and CDATA sections are joined, so the content may be wrapped in `<![CDATA[...]]>`.
+fn read_text<B: BufRead>(
+    reader: &mut Reader<B>,
+    buf: &mut Vec<u8>,
+    ctx: &mut ReadContext,
+) -> Result<Option<String>, ReadError> {
+    let mut text: Option<String> = None;
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Id" => {
-                    must_read_text_as_date!(activity.id, reader, buf);
-                }
-                b"Lap" => {
-                    activity.laps.push(read_activity_lap(reader, b"Lap", e)?);
-                }
-                b"Notes" => {
-                    opt_read_text!(activity.notes, reader, buf);
-                }
-                b"Training" => {
-                    activity.training = Some(read_training(reader, b"Training")?);
-                }
-                b"Creator" => {
-                    let e_type = read_type(e)?;
-                    if e_type.as_str() == "Application_t" {
-                        activity.creator = Some(SourceType::Application(read_application(
-                            reader, b"Creator",
-                        )?));
-                    } else if e_type.as_str() == "Device_t" {
-                        activity.creator =
-                            Some(SourceType::Device(read_device(reader, b"Creator")?));
-                    }
-                }
-                _ => (),
-            },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
+        match next_event(reader, buf, ctx) {
+            Ok(Event::Text(ref t)) => text
+                .get_or_insert_with(String::new)
+                .push_str(&t.unescape()?),
+            Ok(Event::CData(ref c)) => text
+                .get_or_insert_with(String::new)
+                .push_str(&reader.decoder().decode(c)?),
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
-        buf.clear();
     }
-    Ok(activity)
+    Ok(text)
 }
 
-fn read_activity_lap<B: BufRead>(
+fn read_text_as<B: BufRead, T>(
+    reader: &mut Reader<B>,
+    ctx: &mut ReadContext,
+) -> Result<Option<T>, ReadError>
+where
+    T: FromStr,
+    ReadError: From<T::Err>,
+{
+    match read_text(reader, &mut Vec::new(), ctx)? {
+        Some(t) => Ok(Some(T::from_str(&t)?)),
+        None => Ok(None),
+    }
+}
+
+/// Reads the content of the element up to its end as raw XML.
+fn read_raw_xml<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-    lap_element: &BytesStart,
-) -> Result<ActivityLap, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<String, ReadError> {
     let mut buf = Vec::new();
-    let mut a_lap = ActivityLap::default();
-    for ar in lap_element.attributes() {
-        if let Ok(a) = ar {
-            match a.key.into_inner() {
-                b"StartTime" => {
-                    a_lap.start_time =
-                        DateTime::parse_from_rfc3339(&a.unescape_value()?.to_owned())?.into();
-                }
-                _ => (),
-            }
-        }
-    }
+    let mut writer = Writer::new(Vec::new());
+    let mut depth = 0;
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"TotalTimeSeconds" => {
-                    must_read_text_as!(a_lap.total_time_seconds, reader, buf, f64);
-                }
-                b"DistanceMeters" => {
-                    must_read_text_as!(a_lap.distance_meters, reader, buf, f64);
-                }
-                b"MaximumSpeed" => {
-                    opt_read_text_as!(a_lap.maximum_speed, reader, buf, f64);
-                }
-                b"Calories" => {
-                    must_read_text_as!(a_lap.calories, reader, buf, u16);
-                }
-                b"AverageHeartRateBpm" => {
-                    opt_read_value_as!(a_lap.average_heart_rate_bpm, reader, buf, u8);
-                }
-                b"MaximumHeartRateBpm" => {
-                    opt_read_value_as!(a_lap.maximum_heart_rate_bpm, reader, buf, u8);
-                }
-                b"Intensity" => {
-                    must_read_text_as!(a_lap.intensity, reader, buf, Intensity);
-                }
-                b"Cadence" => {
-                    opt_read_text_as!(a_lap.cadence, reader, buf, u8);
-                }
-                b"TriggerMethod" => {
-                    must_read_text_as!(a_lap.trigger_method, reader, buf, TriggerMethod);
-                }
-                b"Track" => {
-                    let tps = read_track(reader, b"Track")?;
-                    for tp in tps {
-                        a_lap.track_points.push(tp);
-                    }
-                }
-                b"Notes" => {
-                    opt_read_text!(a_lap.notes, reader, buf);
-                }
-                b"LX" => {
-                    a_lap.extension = Some(read_activity_lap_extension(reader, b"LX")?);
-                }
-                _ => (),
-            },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag && depth == 0 => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Ok(event) => {
+                // the content is kept as is, its elements aren't read
+                ctx.unrecognized();
+                match &event {
+                    Event::Start(_) => depth += 1,
+                    Event::End(_) => depth -= 1,
+                    _ => (),
                 }
+                writer.write_event(event)?;
             }
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
         }
         buf.clear();
     }
-    Ok(a_lap)
+    Ok(reader.decoder().decode(&writer.into_inner())?.into_owned())
 }
 
-fn read_track<B: BufRead>(
+fn read_name_attribute<B: BufRead>(
+    reader: &Reader<B>,
+    e: &BytesStart,
+) -> Result<Option<String>, ReadError> {
+    for a in e.attributes().flatten() {
+        if a.key.into_inner() == b"Name" {
+            return Ok(Some(a.decode_and_unescape_value(reader)?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn read_folders<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<Vec<TrackPoint>, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<Folders, ReadError> {
     let mut buf = Vec::new();
-    let mut track = Vec::new();
+    let mut folders = Folders::default();
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Trackpoint" => track.push(read_track_point(reader, b"Trackpoint")?),
-                _ => (),
+                b"History" => folders.history = Some(read_history(reader, b"History", ctx)?),
+                b"Workouts" => folders.workouts = Some(read_workouts(reader, b"Workouts", ctx)?),
+                b"Courses" => folders.courses = Some(read_courses(reader, b"Courses", ctx)?),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(track)
+    Ok(folders)
 }
 
-fn read_track_point<B: BufRead>(
+fn read_history<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<TrackPoint, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<History, ReadError> {
     let mut buf = Vec::new();
-    let mut tp = TrackPoint::default();
+    let mut history = History::default();
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Time" => {
-                    must_read_text_as_date!(tp.time, reader, buf);
+                b"Running" => {
+                    history.running = Some(read_history_folder(reader, b"Running", e, ctx)?);
                 }
-                b"Position" => {
-                    tp.position = Some(read_position(reader, b"Position")?);
+                b"Biking" => {
+                    history.biking = Some(read_history_folder(reader, b"Biking", e, ctx)?);
                 }
-                b"AltitudeMeters" => {
-                    opt_read_text_as!(tp.altitude_meters, reader, buf, f64);
+                b"Other" => {
+                    history.other = Some(read_history_folder(reader, b"Other", e, ctx)?);
                 }
-                b"DistanceMeters" => {
-                    opt_read_text_as!(tp.distance_meters, reader, buf, f64);
+                b"MultiSport" => {
+                    history.multi_sport =
+                        Some(read_multi_sport_folder(reader, b"MultiSport", e, ctx)?);
                 }
-                b"HeartRateBpm" => {
-                    opt_read_value_as!(tp.heart_rate_bpm, reader, buf, u8);
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::Empty(ref e)) => match e.name().into_inner() {
+                b"Running" => {
+                    history.running = Some(HistoryFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..HistoryFolder::default()
+                    });
                 }
-                b"Cadence" => {
-                    opt_read_text_as!(tp.cadence, reader, buf, u8);
+                b"Biking" => {
+                    history.biking = Some(HistoryFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..HistoryFolder::default()
+                    });
                 }
-                b"SensorState" => {
-                    opt_read_text_as!(tp.sensor_state, reader, buf, SensorState);
+                b"Other" => {
+                    history.other = Some(HistoryFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..HistoryFolder::default()
+                    });
                 }
-                b"TPX" => {
-                    tp.extension = Some(read_activity_track_point_extension(reader, b"TPX", e)?);
+                b"MultiSport" => {
+                    history.multi_sport = Some(MultiSportFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..MultiSportFolder::default()
+                    });
                 }
-                _ => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(tp)
+    Ok(history)
 }
 
-fn read_position<B: BufRead>(
+fn read_history_folder<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<Position, ReadError> {
+    folder_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<HistoryFolder, ReadError> {
     let mut buf = Vec::new();
-    let mut pos = Position::default();
+    let mut folder = HistoryFolder {
+        name: read_name_attribute(reader, folder_element)?,
+        ..HistoryFolder::default()
+    };
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"LatitudeDegrees" => {
-                    must_read_text_as!(pos.latitude_degrees, reader, buf, f64);
-                }
-                b"LongitudeDegrees" => {
-                    must_read_text_as!(pos.longitude_degrees, reader, buf, f64);
+                b"Folder" => folder
+                    .folders
+                    .get_or_insert_with(Vec::new)
+                    .push(read_history_folder(reader, b"Folder", e, ctx)?),
+                b"ActivityRef" => folder
+                    .activity_refs
+                    .get_or_insert_with(Vec::new)
+                    .push(read_activity_ref(reader, b"ActivityRef", ctx)?),
+                b"Week" => folder
+                    .weeks
+                    .get_or_insert_with(Vec::new)
+                    .push(read_week(reader, b"Week", e, ctx)?),
+                b"Notes" => {
+                    opt_read_text!(folder.notes, reader, buf, ctx);
                 }
-                _ => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Folder" => folder
+                .folders
+                .get_or_insert_with(Vec::new)
+                .push(HistoryFolder {
+                    name: read_name_attribute(reader, e)?,
+                    ..HistoryFolder::default()
+                }),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(pos)
+    Ok(folder)
 }
 
-fn read_plan<B: BufRead>(
+fn read_multi_sport_folder<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-    plan_element: &BytesStart,
-) -> Result<Plan, ReadError> {
+    folder_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<MultiSportFolder, ReadError> {
     let mut buf = Vec::new();
-    let mut plan = Plan::default();
-    for ar in plan_element.attributes() {
-        if let Ok(a) = ar {
-            match a.key.into_inner() {
-                b"Type" => {
-                    plan.training_type = TrainingType::from_str(&a.unescape_value()?.to_owned())?;
-                }
-                b"IntervalWorkout" => {
-                    plan.interval_workout = bool::from_str(&a.unescape_value()?.to_owned())?;
-                }
-                _ => (),
-            }
-        }
-    }
+    let mut folder = MultiSportFolder {
+        name: read_name_attribute(reader, folder_element)?,
+        ..MultiSportFolder::default()
+    };
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Name" => {
-                    opt_read_text!(plan.name, reader, buf);
+                b"Folder" => folder
+                    .folders
+                    .get_or_insert_with(Vec::new)
+                    .push(read_multi_sport_folder(reader, b"Folder", e, ctx)?),
+                b"MultiSportActivityRef" => folder
+                    .multisport_activity_refs
+                    .get_or_insert_with(Vec::new)
+                    .push(read_activity_ref(reader, b"MultiSportActivityRef", ctx)?),
+                b"Week" => folder
+                    .weeks
+                    .get_or_insert_with(Vec::new)
+                    .push(read_week(reader, b"Week", e, ctx)?),
+                b"Notes" => {
+                    opt_read_text!(folder.notes, reader, buf, ctx);
                 }
-                _ => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Folder" => folder
+                .folders
+                .get_or_insert_with(Vec::new)
+                .push(MultiSportFolder {
+                    name: read_name_attribute(reader, e)?,
+                    ..MultiSportFolder::default()
+                }),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(plan)
+    Ok(folder)
 }
 
-fn read_quick_workout<B: BufRead>(
+/// Reads the `Id` of an activity reference, which is the start time of the referenced activity.
+fn read_activity_ref<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<QuickWorkout, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<DateTime<Utc>, ReadError> {
     let mut buf = Vec::new();
-    let mut quick_workout = QuickWorkout::default();
+    let mut id = None;
     loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"TotalTimeSeconds" => {
-                    must_read_text_as!(quick_workout.total_time_seconds, reader, buf, f64);
-                }
-                b"DistanceMeters" => {
-                    must_read_text_as!(quick_workout.distance_meters, reader, buf, f64);
-                }
-                _ => (),
-            },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Id" => {
+                if let Some(t) = read_text(reader, &mut buf, ctx)? {
+                    id = Some(parse_time(&t, ctx)?);
                 }
             }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(quick_workout)
+    id.ok_or(ReadError::MissingElement("Id"))
 }
 
-fn read_training<B: BufRead>(
+/// Reads the `Id` of a workout or course reference, which is the name of the referenced item.
+fn read_name_ref<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<Training, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<String, ReadError> {
     let mut buf = Vec::new();
-    let mut training = Training::default();
+    let mut id = None;
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Id" => {
+                id = read_text(reader, &mut buf, ctx)?;
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    id.ok_or(ReadError::MissingElement("Id"))
+}
+
+fn read_week<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    week_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Week, ReadError> {
+    let mut buf = Vec::new();
+    let mut week = Week::default();
+    for a in week_element.attributes().flatten() {
+        if a.key.into_inner() == b"StartDay" {
+            let day = NaiveDate::parse_from_str(&a.decode_and_unescape_value(reader)?, "%Y-%m-%d")?;
+            week.start_day = Some(Utc.from_utc_datetime(&day.and_time(NaiveTime::default())));
+        }
+    }
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Notes" => {
+                opt_read_text!(week.notes, reader, buf, ctx);
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(week)
+}
+
+fn read_workouts<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Workouts, ReadError> {
+    let mut buf = Vec::new();
+    let mut workouts = Workouts::default();
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"QuickWorkoutResults" => {
-                    training.quick_workout_results =
-                        Some(read_quick_workout(reader, b"QuickWorkoutResults")?);
+                b"Running" => {
+                    workouts.running = Some(read_workout_folder(reader, b"Running", e, ctx)?);
                 }
-                b"Plan" => {
-                    training.plan = Some(read_plan(reader, b"Plan", e)?);
+                b"Biking" => {
+                    workouts.biking = Some(read_workout_folder(reader, b"Biking", e, ctx)?);
+                }
+                b"Other" => {
+                    workouts.other = Some(read_workout_folder(reader, b"Other", e, ctx)?);
                 }
-                _ => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
+            Ok(Event::Empty(ref e)) => match e.name().into_inner() {
+                b"Running" => {
+                    workouts.running = Some(WorkoutFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..WorkoutFolder::default()
+                    });
+                }
+                b"Biking" => {
+                    workouts.biking = Some(WorkoutFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..WorkoutFolder::default()
+                    });
                 }
+                b"Other" => {
+                    workouts.other = Some(WorkoutFolder {
+                        name: read_name_attribute(reader, e)?,
+                        ..WorkoutFolder::default()
+                    });
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(workouts)
+}
+
+fn read_workout_folder<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    folder_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<WorkoutFolder, ReadError> {
+    let mut buf = Vec::new();
+    let mut folder = WorkoutFolder {
+        name: read_name_attribute(reader, folder_element)?,
+        ..WorkoutFolder::default()
+    };
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Folder" => folder
+                    .folders
+                    .get_or_insert_with(Vec::new)
+                    .push(read_workout_folder(reader, b"Folder", e, ctx)?),
+                b"WorkoutNameRef" => folder
+                    .workout_name_refs
+                    .get_or_insert_with(Vec::new)
+                    .push(read_name_ref(reader, b"WorkoutNameRef", ctx)?),
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Folder" => folder
+                .folders
+                .get_or_insert_with(Vec::new)
+                .push(WorkoutFolder {
+                    name: read_name_attribute(reader, e)?,
+                    ..WorkoutFolder::default()
+                }),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(folder)
+}
+
+fn read_courses<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Courses, ReadError> {
+    let mut buf = Vec::new();
+    let mut courses = Courses::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"CourseFolder" => {
+                courses.course_folder = Some(read_course_folder(reader, b"CourseFolder", e, ctx)?);
             }
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"CourseFolder" => {
+                courses.course_folder = Some(CourseFolder {
+                    name: read_name_attribute(reader, e)?,
+                    ..CourseFolder::default()
+                });
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(training)
+    Ok(courses)
 }
 
-fn read_device<B: BufRead>(reader: &mut Reader<B>, close_tag: &[u8]) -> Result<Device, ReadError> {
+fn read_course_folder<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    folder_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<CourseFolder, ReadError> {
     let mut buf = Vec::new();
-    let mut d = Device::default();
+    let mut folder = CourseFolder {
+        name: read_name_attribute(reader, folder_element)?,
+        ..CourseFolder::default()
+    };
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Name" => {
-                    must_read_text!(d.name, reader, buf);
-                }
-                b"UnitId" => {
-                    must_read_text_as!(d.unit_id, reader, buf, u32);
-                }
-                b"ProductID" => {
-                    must_read_text_as!(d.product_id, reader, buf, u16);
-                }
-                b"Version" => {
-                    d.version = read_version(reader)?;
+                b"Folder" => folder
+                    .folders
+                    .get_or_insert_with(Vec::new)
+                    .push(read_course_folder(reader, b"Folder", e, ctx)?),
+                b"CourseNameRef" => folder
+                    .course_name_refs
+                    .get_or_insert_with(Vec::new)
+                    .push(read_name_ref(reader, b"CourseNameRef", ctx)?),
+                b"Notes" => {
+                    opt_read_text!(folder.notes, reader, buf, ctx);
                 }
-                _ => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Folder" => folder
+                .folders
+                .get_or_insert_with(Vec::new)
+                .push(CourseFolder {
+                    name: read_name_attribute(reader, e)?,
+                    ..CourseFolder::default()
+                }),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(folder)
+}
+
+fn read_activity_list<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<ActivityList, ReadError> {
+    let mut buf = Vec::new();
+    let mut al = ActivityList::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Activity" => al
+                .activities
+                .push(read_activity(reader, b"Activity", e, ctx)?),
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Activity" => {
+                ctx.stats.activities += 1;
+                al.activities.push(read_activity_attributes(reader, e)?);
             }
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"MultiSportSession" => al
+                .multi_sport_sessions
+                .push(read_multi_sport_session(reader, b"MultiSportSession", ctx)?),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(d)
+    Ok(al)
 }
 
-fn read_application<B: BufRead>(
+fn read_multi_sport_session<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<Application, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<MultiSportSession, ReadError> {
     let mut buf = Vec::new();
-    let mut a = Application::default();
+    let mut session = MultiSportSession::default();
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Name" => {
-                    must_read_text!(a.name, reader, buf);
+                b"Id" => {
+                    if let Some(t) = read_text(reader, &mut buf, ctx)? {
+                        session.id = Some(parse_time(&t, ctx)?);
+                    }
                 }
-                b"Build" => a.build = read_build(reader)?,
-                b"LangID" => {
-                    must_read_text!(a.lang_id, reader, buf);
+                b"Notes" => {
+                    opt_read_text!(session.notes, reader, buf, ctx);
                 }
-                b"PartNumber" => {
-                    must_read_text!(a.part_number, reader, buf);
+                b"FirstSport" | b"NextSport" => {
+                    let sport = read_multi_activity(reader, e.name().into_inner(), ctx)?;
+                    session.sports.get_or_insert_with(Vec::new).push(sport);
                 }
-                _ => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
-            Err(e) => {
-                return Err(ReadError::XmlReadError(e));
-            }
-            _ => (),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    return Ok(a);
+    Ok(session)
 }
 
-fn read_build<B: BufRead>(reader: &mut Reader<B>) -> Result<Build, ReadError> {
+/// Reads a `FirstSport` or a `NextSport` of a multi-sport session.
+fn read_multi_activity<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<MultiActivity, ReadError> {
     let mut buf = Vec::new();
-    let mut build = Build::default();
+    let mut multi_activity = MultiActivity::default();
+    let epoch = Utc.timestamp_opt(0, 0).unwrap();
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Version" => build.version = read_version(reader)?,
-                b"Time" => {
-                    opt_read_text!(build.time, reader, buf);
+                b"Transition" => {
+                    multi_activity.transition =
+                        Some(read_activity_lap(reader, b"Transition", e, epoch, ctx)?);
                 }
-                b"Build" => {
-                    opt_read_text!(build.builder, reader, buf);
-                }
-                b"Type" => {
-                    opt_read_text_as!(build.build_type, reader, buf, BuildType);
+                b"Activity" => {
+                    multi_activity.activity = Some(read_activity(reader, b"Activity", e, ctx)?);
                 }
-                _ => (),
-            },
-            Ok(Event::End(ref e)) => match e.name().into_inner() {
-                b"Build" => break,
-                _ => (),
+                _ => ctx.unrecognized(),
             },
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Activity" => {
+                ctx.stats.activities += 1;
+                multi_activity.activity = Some(read_activity_attributes(reader, e)?);
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(build)
+    Ok(multi_activity)
+}
+
+pub(crate) fn read_activity_attributes<B: BufRead>(
+    reader: &Reader<B>,
+    activity_element: &BytesStart,
+) -> Result<Activity, ReadError> {
+    let mut activity = Activity::default();
+    for a in activity_element.attributes().flatten() {
+        if a.key.into_inner() == b"Sport" {
+            activity.sport = Sport::from_str(&a.decode_and_unescape_value(reader)?)?;
+        }
+    }
+    Ok(activity)
 }
 
-fn read_version<B: BufRead>(reader: &mut Reader<B>) -> Result<Version, ReadError> {
+pub(crate) fn read_activity<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    activity_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Activity, ReadError> {
     let mut buf = Vec::new();
-    let mut version = Version::default();
+    let mut activity = read_activity_attributes(reader, activity_element)?;
+    // start time of the laps missing one and any timed track point
+    let mut lap_start_time = Utc.timestamp_opt(0, 0).unwrap();
+    // non-conformant files put the sport into a child element
+    let has_sport_attribute = activity_element
+        .attributes()
+        .flatten()
+        .any(|a| a.key.into_inner() == b"Sport");
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"VersionMajor" => {
-                    must_read_text_as!(version.version_major, reader, buf, u16);
+                b"Id" => {
+                    must_read_text_as_date!(activity.id, reader, buf, ctx);
+                    lap_start_time = activity.id;
                 }
-                b"VersionMinor" => {
-                    must_read_text_as!(version.version_minor, reader, buf, u16);
+                b"Sport" if !has_sport_attribute => {
+                    must_read_text_as!(activity.sport, reader, buf, Sport, ctx);
                 }
-                b"BuildMajor" => {
-                    opt_read_text_as!(version.build_major, reader, buf, u16);
+                b"Lap" => {
+                    activity
+                        .laps
+                        .push(read_activity_lap(reader, b"Lap", e, lap_start_time, ctx)?);
                 }
-                b"BuildMinor" => {
-                    opt_read_text_as!(version.build_minor, reader, buf, u16);
+                b"Notes" => {
+                    opt_read_text!(activity.notes, reader, buf, ctx);
                 }
-                _ => (),
-            },
-            Ok(Event::End(ref e)) => match e.name().into_inner() {
-                b"Version" => break,
-                _ => (),
+                b"Training" => {
+                    activity.training = Some(read_training(reader, b"Training", ctx)?);
+                }
+                b"Extensions" => {
+                    activity.extensions = Some(read_raw_xml(reader, b"Extensions", ctx)?);
+                }
+                b"Creator" => activity.creator = read_source(reader, b"Creator", e, ctx)?,
+                _ => ctx.unrecognized(),
             },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(version)
+    ctx.stats.activities += 1;
+    Ok(activity)
 }
 
-fn read_activity_track_point_extension<B: BufRead>(
+fn read_activity_lap<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-    tpx_element: &BytesStart,
-) -> Result<ActivityTrackPointExtension, ReadError> {
+    lap_element: &BytesStart,
+    default_start_time: DateTime<Utc>,
+    ctx: &mut ReadContext,
+) -> Result<ActivityLap, ReadError> {
     let mut buf = Vec::new();
-    let mut ate = ActivityTrackPointExtension::default();
-    for ar in tpx_element.attributes() {
-        if let Ok(a) = ar {
-            match a.key.into_inner() {
-                b"CadenceSensor" => {
-                    ate.cadence_sensor = Some(CadenceSensorType::from_str(
-                        &a.unescape_value()?.to_owned(),
-                    )?);
-                }
-                _ => (),
-            }
+    let mut a_lap = ActivityLap::default();
+    let mut has_start_time = false;
+    let mut has_intensity = false;
+    let mut has_distance = false;
+    for a in lap_element.attributes().flatten() {
+        if a.key.into_inner() == b"StartTime" {
+            a_lap.start_time = parse_time(&a.decode_and_unescape_value(reader)?, ctx)?;
+            has_start_time = true;
         }
     }
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"Speed" => {
-                    opt_read_text_as!(ate.speed, reader, buf, f64);
+                b"TotalTimeSeconds" => {
+                    must_read_text_as!(a_lap.total_time_seconds, reader, buf, f64, ctx);
                 }
-                b"RunCadence" => {
-                    opt_read_text_as!(ate.run_cadence, reader, buf, u8);
+                b"DistanceMeters" => {
+                    let factor = distance_unit_factor(reader, e, ctx)?;
+                    must_read_text_as!(a_lap.distance_meters, reader, buf, f64, ctx);
+                    a_lap.distance_meters *= factor;
+                    has_distance = true;
                 }
-                b"Watts" => {
-                    opt_read_text_as!(ate.watts, reader, buf, u16);
+                b"MaximumSpeed" => {
+                    opt_read_text_as!(a_lap.maximum_speed, reader, buf, f64, ctx);
+                }
+                b"Calories" => {
+                    must_read_text_as!(a_lap.calories, reader, buf, u16, ctx);
+                }
+                b"AverageHeartRateBpm" if is_percent_of_max(reader, e)? => {
+                    opt_read_value_as!(a_lap.average_heart_rate_percent, reader, buf, u8, ctx);
+                }
+                b"AverageHeartRateBpm" => {
+                    opt_read_value_as!(a_lap.average_heart_rate_bpm, reader, buf, u8, ctx);
+                }
+                b"MaximumHeartRateBpm" if is_percent_of_max(reader, e)? => {
+                    opt_read_value_as!(a_lap.maximum_heart_rate_percent, reader, buf, u8, ctx);
+                }
+                b"MaximumHeartRateBpm" => {
+                    opt_read_value_as!(a_lap.maximum_heart_rate_bpm, reader, buf, u8, ctx);
+                }
+                b"Intensity" => {
+                    must_read_text_as!(a_lap.intensity, reader, buf, Intensity, ctx);
+                    has_intensity = true;
+                }
+                b"Cadence" => {
+                    opt_read_cadence!(a_lap.cadence, reader, buf, ctx);
+                }
+                b"TriggerMethod" => {
+                    must_read_text_as!(a_lap.trigger_method, reader, buf, TriggerMethod, ctx);
+                }
+                b"Track" if ctx.options.skip_track_points => {
+                    skip_element(reader, ctx)?;
                 }
-                _ => (),
+                b"Track" => {
+                    let tps = read_track(reader, b"Track", ctx)?;
+                    for tp in tps {
+                        a_lap.track_points.push(tp);
+                    }
+                }
+                b"Notes" => {
+                    opt_read_text!(a_lap.notes, reader, buf, ctx);
+                }
+                // the lap extension is read from its child
+                b"Extensions" => (),
+                b"LX" => {
+                    a_lap.extension = Some(read_activity_lap_extension(reader, b"LX", ctx)?);
+                }
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    if !has_intensity && ctx.options.strict {
+        return Err(ReadError::MissingElement("Intensity"));
+    }
+    if !has_start_time {
+        if ctx.options.strict {
+            return Err(ReadError::MissingAttribute("StartTime"));
+        }
+        // the time of the first track point, not the time of reading
+        a_lap.start_time = a_lap
+            .track_points
+            .iter()
+            .find_map(|tp| tp.time)
+            .unwrap_or(default_start_time);
+    }
+    if !has_distance && ctx.options.lenient {
+        let mut distances = a_lap
+            .track_points
+            .iter()
+            .filter_map(|tp| tp.distance_meters);
+        if let Some(first) = distances.next() {
+            a_lap.distance_meters = distances.next_back().unwrap_or(first) - first;
+            ctx.stats.coercions.lap_distance_backfilled += 1;
+        }
+    }
+    ctx.stats.laps += 1;
+    ctx.stats.lap_track_points.push(a_lap.track_points.len());
+    Ok(a_lap)
+}
+
+fn read_track<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Vec<TrackPoint>, ReadError> {
+    let mut buf = Vec::new();
+    let mut track = Vec::new();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Trackpoint" => {
+                let tp = read_track_point(reader, b"Trackpoint", ctx)?;
+                ctx.stats.track_points += 1;
+                if tp.position.is_some() {
+                    ctx.stats.track_points_with_position += 1;
+                }
+                if tp.heart_rate_bpm.is_some() {
+                    ctx.stats.track_points_with_heart_rate += 1;
                 }
+                track.push(tp);
             }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(ate)
+    Ok(track)
 }
 
-fn read_activity_lap_extension<B: BufRead>(
+fn read_track_point<B: BufRead>(
     reader: &mut Reader<B>,
     close_tag: &[u8],
-) -> Result<ActivityLapExtension, ReadError> {
+    ctx: &mut ReadContext,
+) -> Result<TrackPoint, ReadError> {
     let mut buf = Vec::new();
-    let mut ate = ActivityLapExtension::default();
+    let mut tp = TrackPoint::default();
     loop {
-        match reader.read_event_into(&mut buf) {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e))
+                if !ctx.options.trackpoint_fields.allows(e.name().into_inner()) =>
+            {
+                skip_element(reader, ctx)?;
+            }
+            // the extension is matched by its local name, as it's usually written with a prefix
+            Ok(Event::Start(ref e))
+                if matches!(
+                    e.local_name().into_inner(),
+                    b"TPX" | b"ActivityTrackpointExtension"
+                ) =>
+            {
+                tp.extension = Some(read_activity_track_point_extension(
+                    reader,
+                    e.name().into_inner(),
+                    e,
+                    ctx,
+                )?);
+            }
             Ok(Event::Start(ref e)) => match e.name().into_inner() {
-                b"AvgSpeed" => {
-                    opt_read_text_as!(ate.avg_speed, reader, buf, f64);
+                b"Time" => {
+                    if let Some(t) = read_text(reader, &mut buf, ctx)? {
+                        tp.time = Some(parse_time(&t, ctx)?);
+                    }
                 }
-                b"MaxBikeCadence" => {
-                    opt_read_text_as!(ate.max_bike_cadence, reader, buf, u8);
+                b"Position" => {
+                    tp.position = Some(read_position(reader, b"Position", ctx)?);
                 }
-                b"AvgRunCadence" => {
-                    opt_read_text_as!(ate.avg_run_cadence, reader, buf, u8);
+                b"AltitudeMeters" => {
+                    opt_read_altitude!(tp.altitude_meters, reader, buf, ctx);
                 }
-                b"MaxRunCadence" => {
-                    opt_read_text_as!(ate.max_run_cadence, reader, buf, u8);
+                b"DistanceMeters" => {
+                    let factor = distance_unit_factor(reader, e, ctx)?;
+                    opt_read_text_as!(tp.distance_meters, reader, buf, f64, ctx);
+                    tp.distance_meters = tp.distance_meters.map(|d| d * factor);
                 }
-                b"Steps" => {
-                    opt_read_text_as!(ate.steps, reader, buf, u16);
+                b"HeartRateBpm" => {
+                    opt_read_value_as!(tp.heart_rate_bpm, reader, buf, u8, ctx);
                 }
-                b"AvgWatts" => {
-                    opt_read_text_as!(ate.avg_watts, reader, buf, u16);
+                b"Cadence" => {
+                    opt_read_cadence!(tp.cadence, reader, buf, ctx);
                 }
-                b"MaxWatts" => {
-                    opt_read_text_as!(ate.max_watts, reader, buf, u16);
+                b"SensorState" => {
+                    opt_read_text_as!(tp.sensor_state, reader, buf, SensorState, ctx);
                 }
-                _ => (),
+                // the track point extension is read from its child
+                b"Extensions" => (),
+                _ => ctx.unrecognized(),
             },
-            Ok(Event::End(ref e)) => {
-                if e.name().into_inner() == close_tag {
-                    break;
-                }
-            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
             Err(e) => return Err(ReadError::XmlReadError(e)),
-            _ => (),
+            _ => ctx.unrecognized(),
         }
         buf.clear();
     }
-    Ok(ate)
+    Ok(tp)
 }
 
-#[cfg(test)]
-mod tests {
+fn read_position<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Position, ReadError> {
+    let mut buf = Vec::new();
+    let mut pos = Position::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"LatitudeDegrees" => {
+                    must_read_text_as!(pos.latitude_degrees, reader, buf, f64, ctx);
+                }
+                b"LongitudeDegrees" => {
+                    must_read_text_as!(pos.longitude_degrees, reader, buf, f64, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(pos)
+}
+
+fn read_course_list<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<CourseList, ReadError> {
+    let mut buf = Vec::new();
+    let mut cl = CourseList::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Course" => cl
+                .cources
+                .get_or_insert_with(Vec::new)
+                .push(read_course(reader, b"Course", ctx)?),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(cl)
+}
+
+fn read_course<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Course, ReadError> {
+    let mut buf = Vec::new();
+    let mut course = Course::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Name" => {
+                    opt_read_text!(course.name, reader, buf, ctx);
+                }
+                b"Lap" => course
+                    .laps
+                    .get_or_insert_with(Vec::new)
+                    .push(read_course_lap(reader, b"Lap", ctx)?),
+                b"Track" if ctx.options.skip_track_points => {
+                    skip_element(reader, ctx)?;
+                }
+                b"Track" => {
+                    let tps = read_track(reader, b"Track", ctx)?;
+                    course.track_points.get_or_insert_with(Vec::new).extend(tps);
+                }
+                b"Notes" => {
+                    opt_read_text!(course.notes, reader, buf, ctx);
+                }
+                b"CoursePoint" => {
+                    course.course_point = Some(read_course_point(reader, b"CoursePoint", ctx)?);
+                }
+                b"Creator" => course.creator = read_source(reader, b"Creator", e, ctx)?,
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(course)
+}
+
+fn read_course_lap<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<CourseLap, ReadError> {
+    let mut buf = Vec::new();
+    let mut c_lap = CourseLap::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"TotalTimeSeconds" => {
+                    opt_read_text_as!(c_lap.total_time_seconds, reader, buf, f64, ctx);
+                }
+                b"DistanceMeters" => {
+                    opt_read_text_as!(c_lap.distance_meters, reader, buf, f64, ctx);
+                }
+                b"BeginPosition" => {
+                    c_lap.begin_position = Some(read_position(reader, b"BeginPosition", ctx)?);
+                }
+                b"BeginAltitudeMeters" => {
+                    opt_read_altitude!(c_lap.begin_altitude_meters, reader, buf, ctx);
+                }
+                b"EndPosition" => {
+                    c_lap.end_position = Some(read_position(reader, b"EndPosition", ctx)?);
+                }
+                b"EndAltitudeMeters" => {
+                    opt_read_altitude!(c_lap.end_altitude_meters, reader, buf, ctx);
+                }
+                b"AverageHeartRateBpm" => {
+                    opt_read_value_as!(c_lap.average_heart_rate_bpm, reader, buf, u8, ctx);
+                }
+                b"MaximumHeartRateBpm" => {
+                    opt_read_value_as!(c_lap.maximum_heart_rate_bpm, reader, buf, u8, ctx);
+                }
+                b"Intensity" => {
+                    opt_read_text_as!(c_lap.intensity, reader, buf, Intensity, ctx);
+                }
+                b"Cadence" => {
+                    opt_read_cadence!(c_lap.cadence, reader, buf, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(c_lap)
+}
+
+fn read_course_point<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<CoursePoint, ReadError> {
+    let mut buf = Vec::new();
+    let mut point = CoursePoint::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Name" => {
+                    opt_read_text!(point.name, reader, buf, ctx);
+                }
+                b"Time" => {
+                    if let Some(t) = read_text(reader, &mut buf, ctx)? {
+                        point.time = Some(parse_time(&t, ctx)?);
+                    }
+                }
+                b"Position" => {
+                    point.position = Some(read_position(reader, b"Position", ctx)?);
+                }
+                b"AltitudeMeters" => {
+                    opt_read_altitude!(point.altitude_meters, reader, buf, ctx);
+                }
+                b"PointType" => {
+                    opt_read_text_as!(point.point_type, reader, buf, CoursePointType, ctx);
+                }
+                b"Notes" => {
+                    opt_read_text!(point.notes, reader, buf, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(point)
+}
+
+fn read_workout_list<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<WorkoutList, ReadError> {
+    let mut buf = Vec::new();
+    let mut workout_list = WorkoutList::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Workout" => workout_list
+                .workouts
+                .get_or_insert_with(Vec::new)
+                .push(read_workout(reader, b"Workout", e, ctx)?),
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(workout_list)
+}
+
+fn read_workout<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    workout_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Workout, ReadError> {
+    let mut buf = Vec::new();
+    let mut workout = Workout::default();
+    for a in workout_element.attributes().flatten() {
+        if a.key.into_inner() == b"Sport" {
+            workout.sport = Some(Sport::from_str(&a.decode_and_unescape_value(reader)?)?);
+        }
+    }
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Name" => {
+                    opt_read_text!(workout.name, reader, buf, ctx);
+                }
+                b"Step" => workout
+                    .steps
+                    .get_or_insert_with(Vec::new)
+                    .push(read_step(reader, b"Step", e, ctx)?),
+                b"ScheduledOn" => {
+                    if let Some(day) = read_text_as::<B, NaiveDate>(reader, ctx)? {
+                        workout.scheduled_on =
+                            Some(Utc.from_utc_datetime(&day.and_time(NaiveTime::default())));
+                    }
+                }
+                b"Notes" => {
+                    opt_read_text!(workout.notes, reader, buf, ctx);
+                }
+                b"Creator" => workout.creator = read_source(reader, b"Creator", e, ctx)?,
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(workout)
+}
+
+fn read_step<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    step_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<StepType, ReadError> {
+    match read_type(reader, step_element)?.as_str() {
+        "Step_t" => Ok(StepType::Step(read_single_step(reader, close_tag, ctx)?)),
+        "Repeat_t" => Ok(StepType::Repeat(read_repeat(reader, close_tag, ctx)?)),
+        t => Err(ReadError::UnknownType(t.to_string())),
+    }
+}
+
+fn read_repeat<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Repeat, ReadError> {
+    let mut buf = Vec::new();
+    let mut repeat = Repeat::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"StepId" => {
+                    opt_read_text_as!(repeat.step_id, reader, buf, u8, ctx);
+                }
+                b"Repetitions" => {
+                    opt_read_text_as!(repeat.repetitions, reader, buf, u8, ctx);
+                }
+                b"Child" => repeat
+                    .children
+                    .get_or_insert_with(Vec::new)
+                    .push(read_step(reader, b"Child", e, ctx)?),
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(repeat)
+}
+
+fn read_single_step<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Step, ReadError> {
+    let mut buf = Vec::new();
+    let mut step = Step::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"StepId" => {
+                    opt_read_text_as!(step.step_id, reader, buf, u8, ctx);
+                }
+                b"Name" => {
+                    if let Some(name) = read_text(reader, &mut buf, ctx)? {
+                        step.name = Some(name.trim().to_string());
+                    }
+                }
+                b"Intensity" => {
+                    opt_read_text_as!(step.intensity, reader, buf, Intensity, ctx);
+                }
+                b"Duration" => step.duration = read_duration(reader, b"Duration", e, ctx)?,
+                b"Target" => step.target = Some(read_target(reader, b"Target", e, ctx)?),
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::Empty(ref e)) if e.name().into_inner() == b"Target" => {
+                // only None_t target has no content
+                step.target = Some(Target::None);
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(step)
+}
+
+/// Reads the `Duration` of the `duration_element` type, `UserInitiated_t` duration is read as `None`.
+fn read_duration<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    duration_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Option<Duration>, ReadError> {
+    let duration_type = read_type(reader, duration_element)?;
+    let mut buf = Vec::new();
+    let mut duration = None;
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match (duration_type.as_str(), e.name().into_inner()) {
+                ("Time_t", b"Seconds") => duration = read_text_as(reader, ctx)?.map(Duration::Time),
+                ("Distance_t", b"Meters") => {
+                    duration = read_text_as(reader, ctx)?.map(Duration::Distance)
+                }
+                ("HeartRateAbove_t", b"HeartRate") => {
+                    duration = read_heart_rate_value(reader, b"HeartRate", ctx)?
+                        .map(Duration::HeartRateAbove)
+                }
+                ("HeartRateBelow_t", b"HeartRate") => {
+                    duration = read_heart_rate_value(reader, b"HeartRate", ctx)?
+                        .map(Duration::HeartRateBelow)
+                }
+                ("CaloriesBurned_t", b"Calories") => {
+                    duration = read_text_as(reader, ctx)?.map(Duration::CaloriesBurned)
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    match duration_type.as_str() {
+        "Time_t" | "Distance_t" | "HeartRateAbove_t" | "HeartRateBelow_t" | "CaloriesBurned_t"
+        | "UserInitiated_t" => Ok(duration),
+        t => Err(ReadError::UnknownType(t.to_string())),
+    }
+}
+
+fn read_heart_rate_value<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Option<u8>, ReadError> {
+    let mut buf = Vec::new();
+    let mut value = None;
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Value" => {
+                if let Some(t) = read_text(reader, &mut buf, ctx)? {
+                    match numeric_value(&t) {
+                        "" => (),
+                        v => value = Some(u8::from_str(v)?),
+                    }
+                }
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(value)
+}
+
+fn read_target<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    target_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Target, ReadError> {
+    let target_type = read_type(reader, target_element)?;
+    let mut buf = Vec::new();
+    let mut zone = None;
+    let mut cadence = Cadence::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match (target_type.as_str(), e.name().into_inner()) {
+                ("Speed_t", b"SpeedZone") => zone = Some(read_zone(reader, b"SpeedZone", e, ctx)?),
+                ("HeartRate_t", b"HeartRateZone") => {
+                    zone = Some(read_zone(reader, b"HeartRateZone", e, ctx)?)
+                }
+                ("Cadence_t", b"Low") => {
+                    opt_read_text_as!(cadence.low, reader, buf, f64, ctx);
+                }
+                ("Cadence_t", b"High") => {
+                    opt_read_text_as!(cadence.high, reader, buf, f64, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    match target_type.as_str() {
+        "Speed_t" => Ok(Target::Speed(
+            zone.ok_or(ReadError::MissingElement("SpeedZone"))?,
+        )),
+        "HeartRate_t" => Ok(Target::HeartRate(
+            zone.ok_or(ReadError::MissingElement("HeartRateZone"))?,
+        )),
+        "Cadence_t" => Ok(Target::Cadence(cadence)),
+        "None_t" => Ok(Target::None),
+        t => Err(ReadError::UnknownType(t.to_string())),
+    }
+}
+
+fn read_zone<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    zone_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<Zone, ReadError> {
+    match read_type(reader, zone_element)?.as_str() {
+        "PredefinedSpeedZone_t" => Ok(Zone::PredefinedSpeedZone(read_zone_number(
+            reader, close_tag, ctx,
+        )?)),
+        "CustomSpeedZone_t" => Ok(Zone::CustomSpeedZone(read_custom_speed_zone(
+            reader, close_tag, ctx,
+        )?)),
+        "PredefinedHeartRateZone_t" => Ok(Zone::PredefinedHeartRateZone(read_zone_number(
+            reader, close_tag, ctx,
+        )?)),
+        "CustomHeartRateZone_t" => Ok(Zone::CustomHeartRateZone(read_custom_heart_rate_zone(
+            reader, close_tag, ctx,
+        )?)),
+        t => Err(ReadError::UnknownType(t.to_string())),
+    }
+}
+
+fn read_zone_number<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<u8, ReadError> {
+    let mut buf = Vec::new();
+    let mut number = None;
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) if e.name().into_inner() == b"Number" => {
+                number = read_text_as(reader, ctx)?;
+            }
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    number.ok_or(ReadError::MissingElement("Number"))
+}
+
+fn read_custom_speed_zone<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<CustomSpeedZone, ReadError> {
+    let mut buf = Vec::new();
+    let mut zone = CustomSpeedZone::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"ViewAs" => {
+                    opt_read_text_as!(zone.view_as, reader, buf, SpeedType, ctx);
+                }
+                b"LowInMetersPerSecond" => {
+                    opt_read_text_as!(zone.low_in_meters_per_second, reader, buf, f64, ctx);
+                }
+                b"HighInMetersPerSecond" => {
+                    opt_read_text_as!(zone.high_in_meters_per_second, reader, buf, f64, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(zone)
+}
+
+fn read_custom_heart_rate_zone<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<CustomHeartRateZone, ReadError> {
+    let mut buf = Vec::new();
+    let mut zone = CustomHeartRateZone::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Low" => zone.low = read_heart_rate_value(reader, b"Low", ctx)?,
+                b"High" => zone.high = read_heart_rate_value(reader, b"High", ctx)?,
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(zone)
+}
+
+/// Reads the plan along with the quick workout results some files nest into the plan.
+fn read_plan<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    plan_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<(Plan, Option<QuickWorkout>), ReadError> {
+    let mut buf = Vec::new();
+    let mut plan = Plan::default();
+    let mut quick_workout = None;
+    for a in plan_element.attributes().flatten() {
+        match a.key.into_inner() {
+            b"Type" => {
+                plan.training_type = TrainingType::from_str(&a.decode_and_unescape_value(reader)?)?;
+            }
+            b"IntervalWorkout" => {
+                plan.interval_workout = bool::from_str(&a.decode_and_unescape_value(reader)?)?;
+            }
+            _ => (),
+        }
+    }
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Name" => {
+                    opt_read_text!(plan.name, reader, buf, ctx);
+                }
+                b"QuickWorkoutResults" => {
+                    quick_workout = Some(read_quick_workout(reader, b"QuickWorkoutResults", ctx)?);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok((plan, quick_workout))
+}
+
+fn read_quick_workout<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<QuickWorkout, ReadError> {
+    let mut buf = Vec::new();
+    let mut quick_workout = QuickWorkout::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"TotalTimeSeconds" => {
+                    must_read_text_as!(quick_workout.total_time_seconds, reader, buf, f64, ctx);
+                }
+                b"DistanceMeters" => {
+                    must_read_text_as!(quick_workout.distance_meters, reader, buf, f64, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(quick_workout)
+}
+
+fn read_training<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Training, ReadError> {
+    let mut buf = Vec::new();
+    let mut training = Training::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"QuickWorkoutResults" => {
+                    training.quick_workout_results =
+                        Some(read_quick_workout(reader, b"QuickWorkoutResults", ctx)?);
+                }
+                b"Plan" => {
+                    let (plan, quick_workout) = read_plan(reader, b"Plan", e, ctx)?;
+                    training.plan = Some(plan);
+                    if training.quick_workout_results.is_none() {
+                        training.quick_workout_results = quick_workout;
+                    }
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(training)
+}
+
+fn read_device<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Device, ReadError> {
+    let mut buf = Vec::new();
+    let mut d = Device::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Name" => {
+                    must_read_text!(d.name, reader, buf, ctx);
+                }
+                b"UnitId" => {
+                    let text = read_text(reader, &mut buf, ctx)?;
+                    if let Some(unit_id) = parse_required_int("UnitId", text.as_deref(), ctx)? {
+                        d.unit_id = unit_id;
+                    }
+                }
+                b"ProductID" => {
+                    let text = read_text(reader, &mut buf, ctx)?;
+                    if let Some(product_id) = parse_required_int("ProductID", text.as_deref(), ctx)?
+                    {
+                        d.product_id = product_id;
+                    }
+                }
+                b"Version" => {
+                    d.version = read_version(reader, ctx)?;
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::Empty(ref e)) => match e.name().into_inner() {
+                b"UnitId" => {
+                    parse_required_int::<u32>("UnitId", None, ctx)?;
+                }
+                b"ProductID" => {
+                    parse_required_int::<u16>("ProductID", None, ctx)?;
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(d)
+}
+
+fn read_application<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<Application, ReadError> {
+    let mut buf = Vec::new();
+    let mut a = Application::default();
+    let (mut has_lang_id, mut has_part_number) = (false, false);
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Name" => {
+                    must_read_text!(a.name, reader, buf, ctx);
+                }
+                b"Build" => a.build = read_build(reader, ctx)?,
+                b"LangID" => {
+                    let lang_id = read_text(reader, &mut buf, ctx)?.unwrap_or_default();
+                    a.lang_id = parse_lang_id(lang_id, ctx)?;
+                    has_lang_id = true;
+                }
+                b"PartNumber" => {
+                    if let Some(t) = read_text(reader, &mut buf, ctx)? {
+                        a.part_number = t;
+                        has_part_number = true;
+                    }
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => {
+                return Err(ReadError::XmlReadError(e));
+            }
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    if ctx.options.strict {
+        if !has_lang_id {
+            return Err(ReadError::MissingElement("LangID"));
+        }
+        if !has_part_number {
+            return Err(ReadError::MissingElement("PartNumber"));
+        }
+    }
+    Ok(a)
+}
+
+fn read_build<B: BufRead>(
+    reader: &mut Reader<B>,
+    ctx: &mut ReadContext,
+) -> Result<Build, ReadError> {
+    let mut buf = Vec::new();
+    let mut build = Build::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"Version" => build.version = read_version(reader, ctx)?,
+                b"Time" => {
+                    opt_read_text!(build.time, reader, buf, ctx);
+                }
+                // the schema names it `Builder`, but `Build` is found in files as well
+                b"Build" | b"Builder" => {
+                    opt_read_text!(build.builder, reader, buf, ctx);
+                }
+                b"Type" => {
+                    opt_read_text_as!(build.build_type, reader, buf, BuildType, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == b"Build" => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(build)
+}
+
+fn read_version<B: BufRead>(
+    reader: &mut Reader<B>,
+    ctx: &mut ReadContext,
+) -> Result<Version, ReadError> {
+    let mut buf = Vec::new();
+    let mut version = Version::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"VersionMajor" => {
+                    must_read_text_as!(version.version_major, reader, buf, u16, ctx);
+                }
+                b"VersionMinor" => {
+                    must_read_text_as!(version.version_minor, reader, buf, u16, ctx);
+                }
+                b"BuildMajor" => {
+                    opt_read_text_as!(version.build_major, reader, buf, u16, ctx);
+                }
+                b"BuildMinor" => {
+                    opt_read_text_as!(version.build_minor, reader, buf, u16, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == b"Version" => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(version)
+}
+
+fn read_activity_track_point_extension<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    tpx_element: &BytesStart,
+    ctx: &mut ReadContext,
+) -> Result<ActivityTrackPointExtension, ReadError> {
+    let mut buf = Vec::new();
+    let mut ate = ActivityTrackPointExtension::default();
+    for a in tpx_element.attributes().flatten() {
+        if a.key.into_inner() == b"CadenceSensor" {
+            ate.cadence_sensor = Some(CadenceSensorType::from_str(
+                &a.decode_and_unescape_value(reader)?,
+            )?);
+        }
+    }
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.local_name().into_inner() {
+                b"Speed" => {
+                    opt_read_text_as!(ate.speed, reader, buf, f64, ctx);
+                }
+                b"RunCadence" => {
+                    opt_read_text_as!(ate.run_cadence, reader, buf, u8, ctx);
+                }
+                b"Watts" => {
+                    opt_read_text_as!(ate.watts, reader, buf, u16, ctx);
+                }
+                b"CadenceSensor" => {
+                    opt_read_text_as!(ate.cadence_sensor, reader, buf, CadenceSensorType, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(ate)
+}
+
+fn read_activity_lap_extension<B: BufRead>(
+    reader: &mut Reader<B>,
+    close_tag: &[u8],
+    ctx: &mut ReadContext,
+) -> Result<ActivityLapExtension, ReadError> {
+    let mut buf = Vec::new();
+    let mut ate = ActivityLapExtension::default();
+    loop {
+        match next_event(reader, &mut buf, ctx) {
+            Ok(Event::Start(ref e)) => match e.name().into_inner() {
+                b"AvgSpeed" => {
+                    opt_read_text_as!(ate.avg_speed, reader, buf, f64, ctx);
+                }
+                b"MaxBikeCadence" => {
+                    opt_read_text_as!(ate.max_bike_cadence, reader, buf, u8, ctx);
+                }
+                b"AvgRunCadence" => {
+                    opt_read_text_as!(ate.avg_run_cadence, reader, buf, u8, ctx);
+                }
+                b"MaxRunCadence" => {
+                    opt_read_text_as!(ate.max_run_cadence, reader, buf, u8, ctx);
+                }
+                b"Steps" => {
+                    opt_read_text_as!(ate.steps, reader, buf, u16, ctx);
+                }
+                b"AvgWatts" => {
+                    opt_read_text_as!(ate.avg_watts, reader, buf, u16, ctx);
+                }
+                b"MaxWatts" => {
+                    opt_read_text_as!(ate.max_watts, reader, buf, u16, ctx);
+                }
+                b"MaximumSpeed" => {
+                    opt_read_text_as!(ate.max_speed, reader, buf, f64, ctx);
+                }
+                _ => ctx.unrecognized(),
+            },
+            Ok(Event::End(ref e)) if e.name().into_inner() == close_tag => break,
+            Ok(Event::Eof) => return Err(ReadError::UnexpectedEof),
+            Err(e) => return Err(ReadError::XmlReadError(e)),
+            _ => ctx.unrecognized(),
+        }
+        buf.clear();
+    }
+    Ok(ate)
+}
+
+#[cfg(test)]
+mod tests {
     use chrono::{NaiveDate, Utc};
     use validator::{Validate, ValidationErrors};
 
-    use super::*;
+    use super::*;
+
+    /// Reads the element `tcx` starts with by `read`, together with the stats of the read.
+    fn read_fragment_with_stats<T>(
+        tcx: &str,
+        options: ReadOptions,
+        read: impl FnOnce(&mut Reader<&[u8]>, &BytesStart, &mut ReadContext) -> Result<T, ReadError>,
+    ) -> Result<(T, ReadStats), ReadError> {
+        let mut reader = Reader::from_str(tcx);
+        let mut buf = Vec::new();
+        let mut ctx = ReadContext::new(options);
+        match reader.read_event_into(&mut buf).unwrap() {
+            Event::Start(e) => read(&mut reader, &e, &mut ctx).map(|t| (t, ctx.stats)),
+            e => panic!("unexpected event {:?}", e),
+        }
+    }
+
+    /// Reads the element `tcx` starts with by `read`, e.g. a `<SpeedZone>` by `read_zone`.
+    fn read_fragment<T>(
+        tcx: &str,
+        options: ReadOptions,
+        read: impl FnOnce(&mut Reader<&[u8]>, &BytesStart, &mut ReadContext) -> Result<T, ReadError>,
+    ) -> Result<T, ReadError> {
+        read_fragment_with_stats(tcx, options, read).map(|(t, _)| t)
+    }
+
+    fn read_lap_with_stats(
+        tcx: &str,
+        options: ReadOptions,
+    ) -> Result<(ActivityLap, ReadStats), ReadError> {
+        read_fragment_with_stats(tcx, options, |r, e, ctx| {
+            read_activity_lap(r, b"Lap", e, Utc.timestamp_opt(0, 0).unwrap(), ctx)
+        })
+    }
+
+    fn read_lap(tcx: &str, options: ReadOptions) -> Result<ActivityLap, ReadError> {
+        read_lap_with_stats(tcx, options).map(|(lap, _)| lap)
+    }
+
+    fn lenient() -> ReadOptions {
+        ReadOptions {
+            lenient: true,
+            ..ReadOptions::default()
+        }
+    }
+
+    fn strict() -> ReadOptions {
+        ReadOptions {
+            strict: true,
+            ..ReadOptions::default()
+        }
+    }
+
+    #[test]
+    fn read_device_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let device = read_device(&mut reader, b"Creator", &mut ReadContext::default()).unwrap();
+        assert_eq!(
+            Device {
+                name: String::from("Polar Vantage V"),
+                unit_id: 0,
+                product_id: 203,
+                version: Version {
+                    version_major: 5,
+                    version_minor: 1,
+                    build_major: Some(0),
+                    build_minor: Some(0),
+                },
+            },
+            device
+        )
+    }
+
+    #[test]
+    fn read_training_center_db_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(
+            SourceType::Application(Application {
+                name: "Polar Flow Mobile Viewer Android".to_string(),
+                lang_id: "EN".to_string(),
+                part_number: "XXX-XXXXX-XX".to_string(),
+                build: Build {
+                    version: Version {
+                        version_major: 0,
+                        version_minor: 0,
+                        build_major: None,
+                        build_minor: None,
+                    },
+                    build_type: None,
+                    time: None,
+                    builder: None,
+                },
+            }),
+            tc.author.unwrap()
+        );
+    }
+
+    #[test]
+    fn read_activities_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(1, tc.activity_list.as_ref().unwrap().activities.len());
+        assert_eq!(
+            0,
+            tc.activity_list
+                .as_ref()
+                .unwrap()
+                .multi_sport_sessions
+                .len()
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn read_activity_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activity = tc
+            .activity_list
+            .unwrap()
+            .activities
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(Sport::Running, activity.sport);
+        assert_eq!(
+            DateTime::<Utc>::from_utc(
+                NaiveDate::from_ymd_opt(2020, 12, 28)
+                    .unwrap()
+                    .and_hms_milli_opt(13, 36, 16, 453)
+                    .unwrap(),
+                Utc
+            ),
+            activity.id
+        );
+        assert_eq!(
+            SourceType::Device(Device {
+                name: String::from("Polar Vantage V"),
+                unit_id: 0,
+                product_id: 203,
+                version: Version {
+                    version_major: 5,
+                    version_minor: 1,
+                    build_major: Some(0),
+                    build_minor: Some(0),
+                },
+            }),
+            activity.creator.unwrap()
+        );
+        assert_eq!(
+            Training {
+                quick_workout_results: None,
+                plan: Some(Plan {
+                    interval_workout: false,
+                    training_type: TrainingType::Workout,
+                    name: None,
+                }),
+                virtual_partner: false,
+            },
+            activity.training.unwrap()
+        );
+        assert_eq!(10, activity.laps.len());
+    }
+
+    #[test]
+    fn read_biking_activity_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/biking.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activity = tc
+            .activity_list
+            .unwrap()
+            .activities
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(Sport::Biking, activity.sport);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn read_activity_lap_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activity = tc
+            .activity_list
+            .unwrap()
+            .activities
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(10, activity.laps.len());
+        let lap = activity.laps.into_iter().next().unwrap();
+        assert_eq!(1000.0, f64::from_str("1000.0").unwrap());
+        assert_eq!(525.0, lap.total_time_seconds);
+        assert_eq!(1000.0, lap.distance_meters);
+        assert_eq!(Some(2.330555650922987), lap.maximum_speed);
+        assert_eq!(779, lap.calories);
+        assert_eq!(Some(127), lap.average_heart_rate_bpm);
+        assert_eq!(Some(137), lap.maximum_heart_rate_bpm);
+        assert_eq!(Intensity::Active, lap.intensity);
+        assert_eq!(Some(90), lap.cadence);
+        assert_eq!(TriggerMethod::Distance, lap.trigger_method);
+        assert_eq!(true, lap.validate().is_ok());
+        assert_eq!(525, lap.track_points.len());
+        assert_eq!(
+            Some(1.9050631258222792),
+            lap.extension.as_ref().unwrap().avg_speed
+        );
+        assert_eq!(Some(210), lap.extension.as_ref().unwrap().avg_watts);
+        assert_eq!(Some(262), lap.extension.as_ref().unwrap().max_watts);
+    }
+
+    #[test]
+    #[allow(deprecated, clippy::get_first)]
+    fn read_track_point_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activity = tc
+            .activity_list
+            .unwrap()
+            .activities
+            .into_iter()
+            .next()
+            .unwrap();
+        let tp = activity.laps.get(0).unwrap().track_points.get(0).unwrap();
+        assert_eq!(
+            Some(DateTime::<Utc>::from_utc(
+                NaiveDate::from_ymd_opt(2020, 12, 28)
+                    .unwrap()
+                    .and_hms_milli_opt(13, 36, 17, 453)
+                    .unwrap(),
+                Utc
+            )),
+            tp.time
+        );
+        assert_eq!(
+            Some(Position {
+                latitude_degrees: 51.752415,
+                longitude_degrees: 39.18763,
+            }),
+            tp.position
+        );
+        assert_eq!(Some(178.615), tp.altitude_meters);
+        assert_eq!(Some(0.0), tp.distance_meters);
+        assert_eq!(Some(68), tp.heart_rate_bpm);
+        assert_eq!(Some(0), tp.cadence);
+        assert_eq!(Some(SensorState::Present), tp.sensor_state);
+        assert_eq!(Some(98), tp.extension.as_ref().unwrap().watts);
+        let mut tp_count = 0;
+        for l in &activity.laps {
+            tp_count += l.track_points.len();
+        }
+        assert_eq!(4400, tp_count)
+    }
+
+    #[test]
+    fn read_folders_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Folders>
+                <History>
+                    <Running Name="Running">
+                        <Folder Name="Winter">
+                            <ActivityRef><Id>2020-12-01T08:00:00Z</Id></ActivityRef>
+                        </Folder>
+                        <ActivityRef><Id>2020-12-26T10:00:00Z</Id></ActivityRef>
+                        <ActivityRef><Id>2020-12-27T11:30:00Z</Id></ActivityRef>
+                        <ActivityRef><Id><![CDATA[2020-12-28T13:36:16Z]]></Id></ActivityRef>
+                        <Week StartDay="2020-12-28"><Notes>Recovery week</Notes></Week>
+                    </Running>
+                    <Biking Name="Biking"/>
+                    <MultiSport Name="MultiSport">
+                        <MultiSportActivityRef><Id>2020-07-05T07:00:00Z</Id></MultiSportActivityRef>
+                    </MultiSport>
+                </History>
+                <Workouts>
+                    <Running Name="Running">
+                        <WorkoutNameRef><Id>Inter<![CDATA[vals]]></Id></WorkoutNameRef>
+                    </Running>
+                </Workouts>
+                <Courses>
+                    <CourseFolder Name="Courses">
+                        <CourseNameRef><Id>Park loop</Id></CourseNameRef>
+                    </CourseFolder>
+                </Courses>
+            </Folders>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let folders = read_training_center(&mut reader, &mut ReadContext::default())
+            .unwrap()
+            .folders
+            .unwrap();
+        let history = folders.history.unwrap();
+        let running = history.running.unwrap();
+        assert_eq!(Some(String::from("Running")), running.name);
+        let refs = running.activity_refs.unwrap();
+        assert_eq!(3, refs.len());
+        assert_eq!(
+            Utc.with_ymd_and_hms(2020, 12, 28, 13, 36, 16).unwrap(),
+            refs[2]
+        );
+        let nested = &running.folders.unwrap()[0];
+        assert_eq!(Some(String::from("Winter")), nested.name);
+        assert_eq!(1, nested.activity_refs.as_ref().unwrap().len());
+        let week = &running.weeks.unwrap()[0];
+        assert_eq!(Some(String::from("Recovery week")), week.notes);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2020, 12, 28, 0, 0, 0).unwrap()),
+            week.start_day
+        );
+        assert_eq!(None, history.biking.unwrap().activity_refs);
+        assert_eq!(
+            vec![Utc.with_ymd_and_hms(2020, 7, 5, 7, 0, 0).unwrap()],
+            history
+                .multi_sport
+                .unwrap()
+                .multisport_activity_refs
+                .unwrap()
+        );
+        assert_eq!(
+            vec![String::from("Intervals")],
+            folders
+                .workouts
+                .unwrap()
+                .running
+                .unwrap()
+                .workout_name_refs
+                .unwrap()
+        );
+        assert_eq!(
+            vec![String::from("Park loop")],
+            folders
+                .courses
+                .unwrap()
+                .course_folder
+                .unwrap()
+                .course_name_refs
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn read_skip_track_points_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let options = ReadOptions {
+            skip_track_points: true,
+            ..ReadOptions::default()
+        };
+        let tc = read_training_center(&mut reader, &mut ReadContext::new(options)).unwrap();
+        assert!(tc.author.is_some());
+        let activity = tc
+            .activity_list
+            .unwrap()
+            .activities
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(10, activity.laps.len());
+        assert!(activity.laps.iter().all(|l| l.track_points.is_empty()));
+        let lap = activity.laps.first().unwrap();
+        assert_eq!(525.0, lap.total_time_seconds);
+        assert_eq!(TriggerMethod::Distance, lap.trigger_method);
+        assert_eq!(Some(210), lap.extension.as_ref().unwrap().avg_watts);
+        assert!(activity.creator.is_some());
+    }
+
+    #[test]
+    fn read_stats_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let mut ctx = ReadContext::default();
+        read_training_center(&mut reader, &mut ctx).unwrap();
+        let stats = ctx.stats;
+        assert_eq!(1, stats.activities);
+        assert_eq!(10, stats.laps);
+        assert_eq!(10, stats.lap_track_points.len());
+        assert_eq!(525, stats.lap_track_points[0]);
+        assert_eq!(4400, stats.track_points);
+        assert_eq!(4400, stats.lap_track_points.iter().sum::<usize>());
+        assert!(stats.track_points_with_position <= stats.track_points);
+        assert!(stats.track_points_with_heart_rate <= stats.track_points);
+    }
+
+    #[test]
+    fn read_course_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/course.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let course = tc
+            .course_list
+            .unwrap()
+            .cources
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(Some(String::from("Park loop")), course.name);
+        let track_points = course.track_points.unwrap();
+        assert_eq!(3, track_points.len());
+        let tp = track_points.first().unwrap();
+        assert_eq!(
+            Some(Position {
+                latitude_degrees: 51.752415,
+                longitude_degrees: 39.18763,
+            }),
+            tp.position
+        );
+        assert_eq!(Some(178.6), tp.altitude_meters);
+        assert_eq!(Some(0.0), tp.distance_meters);
+        let lap = course.laps.unwrap().into_iter().next().unwrap();
+        assert_eq!(Some(1250.0), lap.total_time_seconds);
+        assert_eq!(Some(4120.5), lap.distance_meters);
+        assert_eq!(
+            Some(Position {
+                latitude_degrees: 51.752512,
+                longitude_degrees: 39.187702,
+            }),
+            lap.end_position
+        );
+        assert_eq!(Some(Intensity::Active), lap.intensity);
+        assert_eq!(Some(178.6), lap.begin_altitude_meters);
+        assert_eq!(Some(180.2), lap.end_altitude_meters);
+        let point = course.course_point.unwrap();
+        assert_eq!(Some(String::from("Summit")), point.name);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2021, 5, 2, 8, 0, 20).unwrap()),
+            point.time
+        );
+        assert_eq!(Some(179.8), point.altitude_meters);
+        assert_eq!(Some(CoursePointType::Summit), point.point_type);
+        assert_eq!(Some(String::from("Top of the hill")), point.notes);
+        match course.creator {
+            Some(SourceType::Device(device)) => {
+                assert_eq!("Garmin Edge 530", device.name);
+                assert_eq!(3318234150, device.unit_id);
+                assert_eq!(3121, device.product_id);
+                assert_eq!(9, device.version.version_major);
+            }
+            creator => panic!("unexpected creator {:?}", creator),
+        }
+    }
+
+    #[test]
+    fn read_lenient_cadence_test() {
+        let tcx = r#"<TrainingCenterDatabase><Activities><Activity Sport="Biking">
+            <Lap StartTime="2020-12-28T13:36:17Z">
+                <Cadence>300</Cadence>
+                <Track>
+                    <Trackpoint>
+                        <Time>2020-12-28T13:36:17Z</Time>
+                        <Cadence>255</Cadence>
+                    </Trackpoint>
+                </Track>
+            </Lap>
+        </Activity></Activities></TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let err = read_training_center(&mut reader, &mut ReadContext::default()).unwrap_err();
+        assert!(matches!(err, ReadError::ParseIntError(_)));
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::new(lenient())).unwrap();
+        let lap = &tc.activity_list.unwrap().activities[0].laps[0];
+        assert_eq!(Some(254), lap.cadence);
+        assert_eq!(Some(254), lap.track_points[0].cadence);
+        assert!(lap.validate().is_ok());
+    }
+
+    #[test]
+    fn read_empty_containers_test() {
+        let tcx = r#"<TrainingCenterDatabase><Activities/></TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(Some(ActivityList::default()), tc.activity_list);
+
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities></Activities>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(Some(ActivityList::default()), tc.activity_list);
+
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities><Activity Sport="Biking"/></Activities>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activities = tc.activity_list.unwrap().activities;
+        assert_eq!(1, activities.len());
+        assert_eq!(Sport::Biking, activities[0].sport);
+        assert!(activities[0].laps.is_empty());
+    }
+
+    #[test]
+    fn read_truncated_test() {
+        let tcx = r#"<TrainingCenterDatabase><Activities><Activity Sport="Biking">"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let err = read_training_center(&mut reader, &mut ReadContext::default()).unwrap_err();
+        assert!(matches!(err, ReadError::UnexpectedEof));
+    }
+
+    #[test]
+    fn read_custom_speed_zone_test() {
+        let tcx = r#"<SpeedZone xsi:type="CustomSpeedZone_t">
+            <ViewAs>Pace</ViewAs>
+            <LowInMetersPerSecond>2.5</LowInMetersPerSecond>
+            <HighInMetersPerSecond>3.125</HighInMetersPerSecond>
+        </SpeedZone>"#;
+        let zone = read_fragment(tcx, ReadOptions::default(), |r, e, ctx| {
+            read_zone(r, b"SpeedZone", e, ctx)
+        })
+        .unwrap();
+        assert_eq!(
+            Zone::CustomSpeedZone(CustomSpeedZone {
+                view_as: Some(SpeedType::Pace),
+                low_in_meters_per_second: Some(2.5),
+                high_in_meters_per_second: Some(3.125),
+            }),
+            zone
+        );
+    }
+
+    #[test]
+    fn read_workouts_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Workouts>
+                <Workout Sport="Running">
+                    <Name>Intervals</Name>
+                    <Step xsi:type="Step_t">
+                        <Duration xsi:type="Time_t"><Seconds>600</Seconds></Duration>
+                        <Target xsi:type="Speed_t">
+                            <SpeedZone xsi:type="CustomSpeedZone_t">
+                                <ViewAs>Pace</ViewAs>
+                                <LowInMetersPerSecond>2.5</LowInMetersPerSecond>
+                                <HighInMetersPerSecond>3</HighInMetersPerSecond>
+                            </SpeedZone>
+                        </Target>
+                    </Step>
+                    <Step xsi:type="Repeat_t">
+                        <StepId>4</StepId>
+                        <Repetitions>3</Repetitions>
+                        <Child xsi:type="Step_t">
+                            <Duration xsi:type="Distance_t"><Meters>400</Meters></Duration>
+                            <Target xsi:type="HeartRate_t">
+                                <HeartRateZone xsi:type="PredefinedHeartRateZone_t">
+                                    <Number>4</Number>
+                                </HeartRateZone>
+                            </Target>
+                        </Child>
+                        <Child xsi:type="Step_t">
+                            <Duration xsi:type="HeartRateBelow_t">
+                                <HeartRate xsi:type="HeartRateInBeatsPerMinute_t">
+                                    <Value>120</Value>
+                                </HeartRate>
+                            </Duration>
+                            <Target xsi:type="None_t"/>
+                        </Child>
+                    </Step>
+                    <ScheduledOn>2021-03-01</ScheduledOn>
+                </Workout>
+            </Workouts>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let workouts = tc.workout_list.unwrap().workouts.unwrap();
+        assert_eq!(1, workouts.len());
+        let workout = &workouts[0];
+        assert_eq!(Some("Intervals".to_string()), workout.name);
+        assert_eq!(Some(Sport::Running), workout.sport);
+        assert_eq!(
+            Some(Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(2021, 3, 1).unwrap().into())),
+            workout.scheduled_on
+        );
+        let steps = workout.steps.as_ref().unwrap();
+        assert_eq!(
+            StepType::Step(Step {
+                duration: Some(Duration::Time(600)),
+                target: Some(Target::Speed(Zone::CustomSpeedZone(CustomSpeedZone {
+                    view_as: Some(SpeedType::Pace),
+                    low_in_meters_per_second: Some(2.5),
+                    high_in_meters_per_second: Some(3.0),
+                }))),
+                ..Step::default()
+            }),
+            steps[0]
+        );
+        assert_eq!(
+            StepType::Repeat(Repeat {
+                children: Some(vec![
+                    StepType::Step(Step {
+                        duration: Some(Duration::Distance(400)),
+                        target: Some(Target::HeartRate(Zone::PredefinedHeartRateZone(4))),
+                        ..Step::default()
+                    }),
+                    StepType::Step(Step {
+                        duration: Some(Duration::HeartRateBelow(120)),
+                        target: Some(Target::None),
+                        ..Step::default()
+                    }),
+                ]),
+                step_id: Some(4),
+                repetitions: Some(3),
+            }),
+            steps[1]
+        );
+    }
+
+    #[test]
+    fn read_workout_creator_test() {
+        let read = |creator: &str, options: ReadOptions| {
+            let tcx = format!(
+                r#"<TrainingCenterDatabase><Workouts>
+                    <Workout Sport="Running"><Name>Intervals</Name>{}</Workout>
+                </Workouts></TrainingCenterDatabase>"#,
+                creator
+            );
+            let mut reader = Reader::from_reader(tcx.as_bytes());
+            read_training_center(&mut reader, &mut ReadContext::new(options))
+                .map(|tc| tc.workout_list.unwrap().workouts.unwrap().remove(0))
+        };
+        let workout = read(
+            r#"<Creator xsi:type="Application_t">
+                <Name>Garmin Connect</Name>
+                <LangID>EN</LangID>
+                <PartNumber>000-00000-00</PartNumber>
+            </Creator>"#,
+            ReadOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(Some("Intervals".to_string()), workout.name);
+        match workout.creator {
+            Some(SourceType::Application(app)) => assert_eq!("Garmin Connect", app.name),
+            other => panic!("unexpected creator {:?}", other),
+        }
+        let untyped = r#"<Creator><Name>Forerunner</Name></Creator>"#;
+        let workout = read(untyped, ReadOptions::default()).unwrap();
+        assert_eq!(Some("Intervals".to_string()), workout.name);
+        assert!(workout.creator.is_none());
+        assert!(matches!(
+            read(untyped, strict()),
+            Err(ReadError::TypeNotDefined)
+        ));
+    }
+
+    #[test]
+    fn read_repeat_zero_repetitions_test() {
+        let tcx = r#"<Step xsi:type="Repeat_t">
+            <StepId>1</StepId>
+            <Repetitions>0</Repetitions>
+            <Child xsi:type="Step_t"></Child>
+        </Step>"#;
+        let step = read_fragment(tcx, ReadOptions::default(), |r, e, ctx| {
+            read_step(r, b"Step", e, ctx)
+        })
+        .unwrap();
+        assert_eq!(
+            StepType::Repeat(Repeat {
+                step_id: Some(1),
+                repetitions: Some(0),
+                children: Some(vec![StepType::Step(Step::default())]),
+            }),
+            step
+        );
+    }
+
+    #[test]
+    fn read_cdata_notes_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities>
+                <Activity Sport="Running">
+                    <Id>2020-12-28T13:36:16.453Z</Id>
+                    <Notes><![CDATA[Hills & <b>wind</b>, 5 < 6]]></Notes>
+                </Activity>
+            </Activities>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activities = tc.activity_list.unwrap().activities;
+        assert_eq!(
+            Some("Hills & <b>wind</b>, 5 < 6".to_string()),
+            activities[0].notes
+        );
+    }
+
+    #[test]
+    fn read_activity_extensions_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities>
+                <Activity Sport="Running">
+                    <Id>2020-12-28T13:36:16.453Z</Id>
+                    <Extensions><Weather><Temp unit="C">-5</Temp></Weather></Extensions>
+                    <Notes>Cold</Notes>
+                </Activity>
+            </Activities>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activity = &tc.activity_list.unwrap().activities[0];
+        assert_eq!(
+            Some(r#"<Weather><Temp unit="C">-5</Temp></Weather>"#.to_string()),
+            activity.extensions
+        );
+        assert_eq!(Some("Cold".to_string()), activity.notes);
+    }
+
+    #[test]
+    fn read_trackpoint_fields_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut reader = Reader::from_reader(tcx_bytes);
+        let mut ctx = ReadContext::new(ReadOptions {
+            trackpoint_fields: TrackPointFields::POSITION,
+            ..ReadOptions::default()
+        });
+        let tc = read_training_center(&mut reader, &mut ctx).unwrap();
+        let activity = &tc.activity_list.unwrap().activities[0];
+        let tp = &activity.laps[0].track_points[0];
+        assert!(tp.position.is_some());
+        assert_eq!(None, tp.heart_rate_bpm);
+        assert_eq!(None, tp.altitude_meters);
+        assert_eq!(None, tp.cadence);
+        assert_eq!(None, tp.extension);
+        assert_eq!(4400, ctx.stats.track_points);
+
+        let fields = TrackPointFields::POSITION | TrackPointFields::HEART_RATE;
+        assert!(fields.contains(TrackPointFields::HEART_RATE));
+        assert!(!fields.contains(TrackPointFields::CADENCE));
+        assert!(TrackPointFields::default().contains(fields));
+    }
+
+    #[test]
+    fn read_activity_sport_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities>
+                <Activity Sport="Biking"><Id>2020-12-28T13:36:16Z</Id></Activity>
+                <Activity><Sport>Other</Sport><Id>2020-12-29T13:36:16Z</Id></Activity>
+                <Activity Sport="Biking"><Sport>Other</Sport></Activity>
+            </Activities>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let sports: Vec<&Sport> = tc
+            .activity_list
+            .as_ref()
+            .unwrap()
+            .activities
+            .iter()
+            .map(|a| &a.sport)
+            .collect();
+        assert_eq!(vec![&Sport::Biking, &Sport::Other, &Sport::Biking], sports);
+    }
+
+    #[test]
+    fn read_multiple_activity_lists_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities>
+                <Activity Sport="Running"><Id>2020-12-28T13:36:16Z</Id></Activity>
+                <Activity Sport="Biking"><Id>2020-12-29T13:36:16Z</Id></Activity>
+            </Activities>
+            <Activities/>
+            <Activities>
+                <Activity Sport="Other"><Id>2020-12-30T13:36:16Z</Id></Activity>
+            </Activities>
+        </TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let mut ctx = ReadContext::default();
+        let tc = read_training_center(&mut reader, &mut ctx).unwrap();
+        let activities = tc.activity_list.unwrap().activities;
+        assert_eq!(3, activities.len());
+        assert_eq!(Sport::Other, activities[2].sport);
+        assert_eq!(3, ctx.stats.activities);
+    }
+
+    #[test]
+    fn read_coercions_test() {
+        let tcx = r#"<TrainingCenterDatabase><Activities><Activity Sport="Biking">
+            <Lap StartTime="2020-12-28T13:36:17Z">
+                <Cadence>90</Cadence>
+                <Track>
+                    <Trackpoint>
+                        <Time>2020-12-28T13:36:17Z</Time>
+                        <Cadence>310</Cadence>
+                    </Trackpoint>
+                    <Trackpoint>
+                        <Time>2020-12-28T13:36:18Z</Time>
+                        <Cadence>254</Cadence>
+                    </Trackpoint>
+                </Track>
+            </Lap>
+        </Activity></Activities></TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let mut ctx = ReadContext::new(lenient());
+        read_training_center(&mut reader, &mut ctx).unwrap();
+        assert_eq!(1, ctx.stats.coercions.cadence_clamped);
+        assert_eq!(0, ctx.stats.coercions.distance_unit_converted);
+    }
+
+    #[test]
+    fn read_padded_value_test() {
+        let tcx = r#"<Trackpoint>
+            <Time>2020-12-28T13:36:17Z</Time>
+            <HeartRateBpm><Value> +127 </Value></HeartRateBpm>
+        </Trackpoint>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tp = read_track_point(&mut reader, b"Trackpoint", &mut ReadContext::default()).unwrap();
+        assert_eq!(Some(127), tp.heart_rate_bpm);
+        assert_eq!("127", numeric_value("\t+127\n"));
+    }
+
+    #[test]
+    fn read_below_sea_level_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>30</TotalTimeSeconds>
+            <DistanceMeters>200</DistanceMeters>
+            <Calories>10</Calories>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+            <Track>
+                <Trackpoint><Time>2020-12-28T13:36:16Z</Time><AltitudeMeters>5.0</AltitudeMeters></Trackpoint>
+                <Trackpoint><Time>2020-12-28T13:36:26Z</Time><AltitudeMeters>-12.5</AltitudeMeters></Trackpoint>
+                <Trackpoint><Time>2020-12-28T13:36:36Z</Time><AltitudeMeters>-430.0</AltitudeMeters></Trackpoint>
+                <Trackpoint><Time>2020-12-28T13:36:46Z</Time><AltitudeMeters>-420.0</AltitudeMeters></Trackpoint>
+            </Track>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
+        let altitudes: Vec<Option<f64>> = lap
+            .track_points
+            .iter()
+            .map(|tp| tp.altitude_meters)
+            .collect();
+        assert_eq!(
+            vec![Some(5.0), Some(-12.5), Some(-430.0), Some(-420.0)],
+            altitudes
+        );
+        assert_eq!(10.0, lap.elevation_gain_meters());
+        assert_eq!(435.0, lap.elevation_loss_meters());
+    }
+
+    #[test]
+    fn read_training_centers_test() {
+        let tcx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase>
+    <Activities><Activity Sport="Running"><Id>2020-12-28T13:36:16Z</Id></Activity></Activities>
+</TrainingCenterDatabase>
+<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase>
+    <Activities><Activity Sport="Biking"><Id>2020-12-29T13:36:16Z</Id></Activity></Activities>
+</TrainingCenterDatabase>
+"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc_dbs = read_training_centers(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(2, tc_dbs.len());
+        let sports: Vec<Sport> = tc_dbs
+            .iter()
+            .map(|tc| {
+                tc.activity_list.as_ref().unwrap().activities[0]
+                    .sport
+                    .clone()
+            })
+            .collect();
+        assert_eq!(vec![Sport::Running, Sport::Biking], sports);
+    }
+
+    #[test]
+    fn read_empty_heart_rate_value_test() {
+        let tcx = r#"<Trackpoint>
+            <Time>2020-12-28T13:36:17Z</Time>
+            <HeartRateBpm><Value> </Value></HeartRateBpm>
+        </Trackpoint>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tp = read_track_point(&mut reader, b"Trackpoint", &mut ReadContext::default()).unwrap();
+        assert_eq!(None, tp.heart_rate_bpm);
+
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>30</TotalTimeSeconds>
+            <DistanceMeters>200</DistanceMeters>
+            <Calories>10</Calories>
+            <AverageHeartRateBpm><Value></Value></AverageHeartRateBpm>
+            <MaximumHeartRateBpm><Value/></MaximumHeartRateBpm>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
+        assert_eq!(None, lap.average_heart_rate_bpm);
+        assert_eq!(None, lap.maximum_heart_rate_bpm);
+        assert_eq!(Intensity::Active, lap.intensity);
+
+        let tcx = "<HeartRateAbove><Value></Value></HeartRateAbove>";
+        let value = read_fragment(tcx, ReadOptions::default(), |r, _, ctx| {
+            read_heart_rate_value(r, b"HeartRateAbove", ctx)
+        });
+        assert_eq!(None, value.unwrap());
+    }
+
+    #[test]
+    fn read_altitude_feet_test() {
+        let tcx = r#"<Trackpoint>
+            <Time>2020-12-28T13:36:17Z</Time>
+            <AltitudeMeters>586</AltitudeMeters>
+        </Trackpoint>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tp = read_track_point(&mut reader, b"Trackpoint", &mut ReadContext::default()).unwrap();
+        assert_eq!(Some(586.0), tp.altitude_meters);
+
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let mut ctx = ReadContext::new(ReadOptions {
+            altitude_feet: true,
+            ..ReadOptions::default()
+        });
+        let tp = read_track_point(&mut reader, b"Trackpoint", &mut ctx).unwrap();
+        assert!((tp.altitude_meters.unwrap() - 178.6128).abs() < 1e-9);
+    }
+
+    #[test]
+    fn read_notes_with_entities_test() {
+        let notes_xml = "Great run &amp; fast &lt;PR&gt;\nNext: 10 &quot;k&quot;";
+        let tcx = format!(
+            r#"<Activity Sport="Running">
+            <Id>2020-12-28T13:36:16Z</Id>
+            <Notes>{}</Notes>
+        </Activity>"#,
+            notes_xml
+        );
+        let activity = read_fragment(&tcx, ReadOptions::default(), |r, e, ctx| {
+            read_activity(r, b"Activity", e, ctx)
+        })
+        .unwrap();
+        let notes = activity.notes.unwrap();
+        assert_eq!("Great run & fast <PR>\nNext: 10 \"k\"", notes);
+        assert_eq!(notes_xml, quick_xml::escape::escape(&notes));
+    }
+
+    #[test]
+    fn read_device_author_test() {
+        let tcx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+    <Author xsi:type="Device_t">
+        <Name>Forerunner 945</Name>
+        <UnitId>3318234150</UnitId>
+        <ProductID>3113</ProductID>
+        <Version>
+            <VersionMajor>19</VersionMajor>
+            <VersionMinor>20</VersionMinor>
+            <BuildMajor>0</BuildMajor>
+            <BuildMinor>0</BuildMinor>
+        </Version>
+    </Author>
+</TrainingCenterDatabase>"#;
+        let mut reader = Reader::from_reader(tcx.as_bytes());
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(
+            Some(SourceType::Device(Device {
+                name: "Forerunner 945".to_string(),
+                unit_id: 3318234150,
+                product_id: 3113,
+                version: Version {
+                    version_major: 19,
+                    version_minor: 20,
+                    build_major: Some(0),
+                    build_minor: Some(0),
+                },
+            })),
+            tc.author
+        );
+    }
+
+    #[test]
+    fn read_element_events_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let (_, stats) = crate::read_with_stats(tcx_bytes, &ReadOptions::default()).unwrap();
+        assert!(stats.element_events.is_empty());
+        let options = ReadOptions {
+            record_element_events: true,
+            ..ReadOptions::default()
+        };
+        let (_, stats) = crate::read_with_stats(tcx_bytes, &options).unwrap();
+        assert_eq!(4400, stats.track_points);
+        let events = stats.element_events;
+        // every element of the sample file but the empty extensions of the plan is read
+        let tcx = std::str::from_utf8(tcx_bytes).unwrap();
+        let start_tags = tcx
+            .split('<')
+            .skip(1)
+            .filter(|t| !t.starts_with('/') && !t.starts_with('?') && !t.starts_with('!'))
+            .count();
+        assert_eq!(start_tags - 1, events.len());
+        assert_eq!(
+            ElementEvent {
+                name: "TrainingCenterDatabase".to_string(),
+                depth: 0
+            },
+            events[0]
+        );
+        assert_eq!(
+            ElementEvent {
+                name: "Activities".to_string(),
+                depth: 1
+            },
+            events[1]
+        );
+        assert_eq!(1, events.iter().filter(|e| e.depth == 0).count());
+
+        let tcx = r#"<TrainingCenterDatabase><Activities>
+            <Activity Sport="Running">
+                <Id>2020-12-28T13:36:16Z</Id>
+                <Weather><Temp unit="C">-5</Temp></Weather>
+                <Notes>Cold</Notes>
+            </Activity>
+        </Activities></TrainingCenterDatabase>"#;
+        let (_, stats) = crate::read_with_stats(tcx.as_bytes(), &options).unwrap();
+        let events: Vec<(&str, usize)> = stats
+            .element_events
+            .iter()
+            .map(|e| (e.name.as_str(), e.depth))
+            .collect();
+        assert_eq!(
+            vec![
+                ("TrainingCenterDatabase", 0),
+                ("Activities", 1),
+                ("Activity", 2),
+                ("Id", 3),
+                ("Notes", 3)
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn read_comments_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/commented.tcx.xml");
+        assert!(crate::read(tcx_bytes).unwrap().comments.is_empty());
+        let options = ReadOptions {
+            record_comments: true,
+            ..ReadOptions::default()
+        };
+        let tc_db = crate::read_with_options(tcx_bytes, &options).unwrap();
+        let texts: Vec<&str> = tc_db
+            .comments
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(vec![" Exported for testing ", " GPS lost "], texts);
+        for (position, text) in &tc_db.comments {
+            assert!(tcx_bytes[*position..].starts_with(format!("<!--{}-->", text).as_bytes()));
+        }
+        assert_eq!(
+            1,
+            tc_db.activity_list.unwrap().activities[0].laps[0]
+                .track_points
+                .len()
+        );
+
+        // comments within skipped content are kept too
+        let options = ReadOptions {
+            record_comments: true,
+            skip_track_points: true,
+            ..ReadOptions::default()
+        };
+        let tc_db = crate::read_with_options(tcx_bytes, &options).unwrap();
+        assert_eq!(2, tc_db.comments.len());
+    }
+
+    #[test]
+    fn read_heart_rate_percent_of_max_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>30</TotalTimeSeconds>
+            <DistanceMeters>200</DistanceMeters>
+            <Calories>10</Calories>
+            <AverageHeartRateBpm xsi:type="HeartRateAsPercentOfMax_t"><Value>75</Value></AverageHeartRateBpm>
+            <MaximumHeartRateBpm xsi:type="HeartRateInBeatsPerMinute_t"><Value>172</Value></MaximumHeartRateBpm>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
+        assert_eq!(Some(75), lap.average_heart_rate_percent);
+        assert_eq!(None, lap.average_heart_rate_bpm);
+        assert_eq!(Some(172), lap.maximum_heart_rate_bpm);
+        assert_eq!(None, lap.maximum_heart_rate_percent);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn read_latin1_test() {
+        let latin1: &[u8] = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>
+<TrainingCenterDatabase xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">
+    <Folders><History><Running Name=\"Entra\xeenement\"/></History></Folders>
+    <Author xsi:type=\"Device_t\">
+        <Name>Montre \xe0 caf\xe9</Name>
+        <UnitId>1</UnitId>
+        <ProductID>2</ProductID>
+        <Version><VersionMajor>1</VersionMajor><VersionMinor>0</VersionMinor></Version>
+    </Author>
+</TrainingCenterDatabase>";
+        let mut reader = Reader::from_reader(latin1);
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        match tc.author {
+            Some(SourceType::Device(device)) => assert_eq!("Montre à café", device.name),
+            author => panic!("unexpected author {:?}", author),
+        }
+        let running = tc.folders.unwrap().history.unwrap().running.unwrap();
+        assert_eq!(Some("Entraînement"), running.name.as_deref());
+    }
 
     #[test]
-    fn read_device_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
-        let mut reader = Reader::from_reader(tcx_bytes);
-        let device = read_device(&mut reader, b"Creator").unwrap();
-        assert_eq!(
-            Device {
-                name: String::from("Polar Vantage V"),
-                unit_id: 0,
-                product_id: 203,
-                version: Version {
-                    version_major: 5,
-                    version_minor: 1,
-                    build_major: Some(0),
-                    build_minor: Some(0),
-                },
-            },
-            device
-        )
+    fn read_lapless_course_test() {
+        let xml = r#"<Course>
+            <Name>No laps</Name>
+            <Track>
+                <Trackpoint>
+                    <Time>2021-05-02T08:00:00Z</Time>
+                    <DistanceMeters>0.0</DistanceMeters>
+                </Trackpoint>
+                <Trackpoint>
+                    <Time>2021-05-02T08:00:10Z</Time>
+                    <DistanceMeters>35.5</DistanceMeters>
+                </Trackpoint>
+            </Track>
+        </Course>"#;
+        let mut reader = Reader::from_str(xml);
+        reader.read_event_into(&mut Vec::new()).unwrap();
+        let course = read_course(&mut reader, b"Course", &mut ReadContext::default()).unwrap();
+        assert_eq!(None, course.laps);
+        let track_points = course.track_points.unwrap();
+        assert_eq!(2, track_points.len());
+        assert_eq!(Some(35.5), track_points[1].distance_meters);
     }
 
     #[test]
-    fn read_training_center_db_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
-        let mut reader = Reader::from_reader(tcx_bytes);
-        let tc = read_training_center(&mut reader).unwrap();
+    fn read_build_test() {
+        let xml = r#"<Build>
+            <Version>
+                <VersionMajor>3</VersionMajor>
+                <VersionMinor>7</VersionMinor>
+            </Version>
+            <Type>Beta</Type>
+            <Time>Mar 25 2021, 10:41:05</Time>
+            <Build>jdoe</Build>
+        </Build>"#;
+        let mut reader = Reader::from_str(xml);
+        reader.read_event_into(&mut Vec::new()).unwrap();
+        let build = read_build(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(3, build.version.version_major);
+        assert_eq!(7, build.version.version_minor);
+        assert_eq!(Some(BuildType::Beta), build.build_type);
+        assert_eq!(Some(String::from("Mar 25 2021, 10:41:05")), build.time);
+        assert_eq!(Some(String::from("jdoe")), build.builder);
+        assert_eq!(Event::Eof, reader.read_event_into(&mut Vec::new()).unwrap());
+
+        let xml = "<Build><Version><VersionMajor>1</VersionMajor><VersionMinor>0</VersionMinor></Version><Builder>jdoe</Builder></Build>";
+        let mut reader = Reader::from_str(xml);
+        reader.read_event_into(&mut Vec::new()).unwrap();
+        let build = read_build(&mut reader, &mut ReadContext::default()).unwrap();
+        assert_eq!(Some(String::from("jdoe")), build.builder);
+    }
+
+    #[test]
+    fn parse_time_formats_test() {
+        let ctx = ReadContext::default();
+        let expected = Utc.with_ymd_and_hms(2020, 12, 28, 13, 36, 16).unwrap();
+        assert_eq!(expected, parse_time("2020-12-28T13:36:16Z", &ctx).unwrap());
+        assert_eq!(expected, parse_time("2020-12-28T13:36:16", &ctx).unwrap());
+        assert_eq!(expected, parse_time("2020-12-28 13:36:16Z", &ctx).unwrap());
+        assert_eq!(expected, parse_time("2020-12-28 13:36:16", &ctx).unwrap());
         assert_eq!(
-            SourceType::Application(Application {
-                name: "Polar Flow Mobile Viewer Android".to_string(),
-                lang_id: "EN".to_string(),
-                part_number: "XXX-XXXXX-XX".to_string(),
-                build: Build {
-                    version: Version {
-                        version_major: 0,
-                        version_minor: 0,
-                        build_major: None,
-                        build_minor: None,
-                    },
-                    build_type: None,
-                    time: None,
-                    builder: None,
-                },
-            }),
-            tc.author.unwrap()
+            expected,
+            parse_time("2020-12-28 16:36:16+03:00", &ctx).unwrap()
+        );
+        assert_eq!(
+            expected + chrono::Duration::milliseconds(500),
+            parse_time("2020-12-28T13:36:16.5", &ctx).unwrap()
         );
+
+        let strict = ReadContext::new(ReadOptions {
+            timestamp_formats: vec![],
+            ..ReadOptions::default()
+        });
+        assert!(matches!(
+            parse_time("2020-12-28T13:36:16", &strict),
+            Err(ReadError::ParseDateError(_))
+        ));
     }
 
     #[test]
-    fn read_activities_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
-        let mut reader = Reader::from_reader(tcx_bytes);
-        let tc = read_training_center(&mut reader).unwrap();
-        assert_eq!(1, tc.activity_list.as_ref().unwrap().activities.len());
+    fn read_lap_extension_max_speed_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>30</TotalTimeSeconds>
+            <DistanceMeters>200</DistanceMeters>
+            <Calories>10</Calories>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+            <Extensions>
+                <LX xmlns="http://www.garmin.com/xmlschemas/ActivityExtension/v2">
+                    <AvgSpeed>6.6</AvgSpeed>
+                    <MaximumSpeed>8.25</MaximumSpeed>
+                </LX>
+            </Extensions>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
+        assert_eq!(None, lap.maximum_speed);
+        assert_eq!(Some(8.25), lap.extension_or_default().max_speed);
+        assert_eq!(Some(8.25), lap.effective_max_speed());
+    }
+
+    #[test]
+    fn read_course_lap_cadence_test() {
+        let read = |cadence: &str, options: ReadOptions| {
+            let tcx = format!(
+                "<Lap><TotalTimeSeconds>60</TotalTimeSeconds><Cadence>{}</Cadence></Lap>",
+                cadence
+            );
+            read_fragment(&tcx, options, |r, _, ctx| read_course_lap(r, b"Lap", ctx))
+        };
+        let lap = read("90", ReadOptions::default()).unwrap();
+        assert_eq!(Some(90), lap.cadence);
+        assert!(lap.validate().is_ok());
+        assert!(matches!(
+            read("300", ReadOptions::default()),
+            Err(ReadError::ParseIntError(_))
+        ));
+        let lap = read("255", ReadOptions::default()).unwrap();
+        assert!(lap.validate().is_err());
+        assert_eq!(Some(254), read("300", lenient()).unwrap().cadence);
+    }
+
+    #[test]
+    fn read_scientific_notation_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>3.6e3</TotalTimeSeconds>
+            <DistanceMeters>1.0E3</DistanceMeters>
+            <Calories>10</Calories>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
+        assert_eq!(1000.0, lap.distance_meters);
+        assert_eq!(3600.0, lap.total_time_seconds);
+    }
+
+    #[test]
+    fn read_lap_without_start_time_test() {
+        let tcx = r#"<Lap>
+            <TotalTimeSeconds>30</TotalTimeSeconds>
+            <DistanceMeters>200</DistanceMeters>
+            <Calories>10</Calories>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+            <Track>
+                <Trackpoint><Time>2020-12-28T13:36:17Z</Time></Trackpoint>
+                <Trackpoint><Time>2020-12-28T13:36:27Z</Time></Trackpoint>
+            </Track>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
         assert_eq!(
-            0,
-            tc.activity_list
-                .as_ref()
-                .unwrap()
-                .multi_sport_sessions
-                .len()
+            Utc.with_ymd_and_hms(2020, 12, 28, 13, 36, 17).unwrap(),
+            lap.start_time
+        );
+        assert!(matches!(
+            read_lap(tcx, strict()),
+            Err(ReadError::MissingAttribute("StartTime"))
+        ));
+
+        // without a timed track point the laps start at the activity id
+        let tcx = r#"<TrainingCenterDatabase><Activities><Activity Sport="Running">
+            <Id>2020-12-28T13:36:16Z</Id>
+            <Lap><TotalTimeSeconds>30</TotalTimeSeconds></Lap>
+            <Lap>
+                <TotalTimeSeconds>30</TotalTimeSeconds>
+                <Track><Trackpoint><Time>2020-12-28T13:36:46Z</Time></Trackpoint></Track>
+            </Lap>
+        </Activity></Activities></TrainingCenterDatabase>"#;
+        let start_times = |options| {
+            let tc = crate::read_with_options(tcx.as_bytes(), &options).unwrap();
+            let activity = &tc.activity_list.unwrap().activities[0];
+            activity
+                .laps
+                .iter()
+                .map(|l| l.start_time)
+                .collect::<Vec<_>>()
+        };
+        let id = Utc.with_ymd_and_hms(2020, 12, 28, 13, 36, 16).unwrap();
+        assert_eq!(
+            vec![id, Utc.with_ymd_and_hms(2020, 12, 28, 13, 36, 46).unwrap()],
+            start_times(ReadOptions::default())
+        );
+        let skipping = ReadOptions {
+            skip_track_points: true,
+            ..ReadOptions::default()
+        };
+        assert_eq!(vec![id, id], start_times(skipping));
+        assert_eq!(
+            Utc.timestamp_opt(0, 0).unwrap(),
+            read_lap(
+                "<Lap><TotalTimeSeconds>30</TotalTimeSeconds></Lap>",
+                lenient()
+            )
+            .unwrap()
+            .start_time
         );
     }
 
     #[test]
-    fn read_activity_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
-        let mut reader = Reader::from_reader(tcx_bytes);
-        let tc = read_training_center(&mut reader).unwrap();
-        let activity = tc
-            .activity_list
-            .unwrap()
-            .activities
-            .into_iter()
-            .next()
-            .unwrap();
-        assert_eq!(Sport::Running, activity.sport);
+    fn read_application_missing_part_number_test() {
+        let read = |part_number: &str, options| {
+            let tcx = format!(
+                r#"<Author xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Application_t">
+                    <LangID>EN</LangID>
+                    <Name>Exporter</Name>
+                    <Build><Version><VersionMajor>1</VersionMajor><VersionMinor>2</VersionMinor></Version></Build>
+                    {}
+                </Author>"#,
+                part_number
+            );
+            read_fragment(&tcx, options, |r, e, ctx| read_source(r, b"Author", e, ctx))
+        };
+        for part_number in &["", "<PartNumber></PartNumber>", "<PartNumber/>"] {
+            assert!(matches!(
+                read(part_number, strict()),
+                Err(ReadError::MissingElement("PartNumber"))
+            ));
+            match read(part_number, ReadOptions::default()).unwrap() {
+                Some(SourceType::Application(application)) => {
+                    assert_eq!("Exporter", application.name);
+                    assert_eq!("EN", application.lang_id);
+                    assert_eq!("", application.part_number);
+                }
+                source => panic!("unexpected source {:?}", source),
+            }
+        }
+    }
+
+    #[test]
+    fn read_application_three_letter_lang_id_test() {
+        let read = |lang_id: &str, options: ReadOptions| {
+            let tcx = format!(
+                r#"<Author xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Application_t">
+                    <Name>Exporter</Name>
+                    <LangID>{}</LangID>
+                    <PartNumber>000-00000-00</PartNumber>
+                </Author>"#,
+                lang_id
+            );
+            let (source, stats) = read_fragment_with_stats(&tcx, options, |r, e, ctx| {
+                read_source(r, b"Author", e, ctx)
+            })?;
+            match source {
+                Some(SourceType::Application(application)) => {
+                    Ok((application.lang_id, stats.coercions.lang_id_shortened))
+                }
+                s => panic!("unexpected source {:?}", s),
+            }
+        };
+        assert!(matches!(
+            read("ENG", strict()),
+            Err(ReadError::InvalidValue("LangID", ref v)) if v == "ENG"
+        ));
+        assert!(matches!(
+            read("", strict()),
+            Err(ReadError::InvalidValue("LangID", ref v)) if v.is_empty()
+        ));
         assert_eq!(
-            DateTime::<Utc>::from_utc(
-                NaiveDate::from_ymd_opt(2020, 12, 28)
-                    .unwrap()
-                    .and_hms_milli_opt(13, 36, 16, 453)
-                    .unwrap(),
-                Utc
-            ),
-            activity.id
+            (String::new(), 0),
+            read("", ReadOptions::default()).unwrap()
         );
         assert_eq!(
-            SourceType::Device(Device {
-                name: String::from("Polar Vantage V"),
-                unit_id: 0,
-                product_id: 203,
-                version: Version {
-                    version_major: 5,
-                    version_minor: 1,
-                    build_major: Some(0),
-                    build_minor: Some(0),
-                },
-            }),
-            activity.creator.unwrap()
+            ("eng".to_string(), 0),
+            read("eng", ReadOptions::default()).unwrap()
         );
+        assert_eq!(("EN".to_string(), 1), read("ENG", lenient()).unwrap());
+        assert_eq!(("de".to_string(), 1), read("ger", lenient()).unwrap());
+        assert_eq!(("XYZ".to_string(), 0), read("XYZ", lenient()).unwrap());
         assert_eq!(
-            Training {
-                quick_workout_results: None,
-                plan: Some(Plan {
-                    interval_workout: false,
-                    training_type: TrainingType::Workout,
-                    name: None,
-                }),
-                virtual_partner: false,
-            },
-            activity.training.unwrap()
+            ("EN".to_string(), 0),
+            read("EN", ReadOptions::default()).unwrap()
         );
-        assert_eq!(10, activity.laps.len());
     }
 
     #[test]
-    fn read_biking_activity_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/biking.tcx.xml");
-        let mut reader = Reader::from_reader(tcx_bytes);
-        let tc = read_training_center(&mut reader).unwrap();
-        let activity = tc
-            .activity_list
-            .unwrap()
-            .activities
-            .into_iter()
-            .next()
-            .unwrap();
-        assert_eq!(Sport::Biking, activity.sport);
+    fn read_lap_without_distance_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>20</TotalTimeSeconds>
+            <Calories>10</Calories>
+            <Intensity>Active</Intensity>
+            <TriggerMethod>Manual</TriggerMethod>
+            <Track>
+                <Trackpoint><Time>2020-12-28T13:36:16Z</Time><DistanceMeters>1200.0</DistanceMeters></Trackpoint>
+                <Trackpoint><Time>2020-12-28T13:36:26Z</Time></Trackpoint>
+                <Trackpoint><Time>2020-12-28T13:36:36Z</Time><DistanceMeters>1275.5</DistanceMeters></Trackpoint>
+            </Track>
+        </Lap>"#;
+        let read = |options| {
+            let (lap, stats) = read_lap_with_stats(tcx, options).unwrap();
+            (lap.distance_meters, stats.coercions.lap_distance_backfilled)
+        };
+        assert_eq!((0.0, 0), read(ReadOptions::default()));
+        assert_eq!((75.5, 1), read(lenient()));
     }
 
     #[test]
-    fn read_activity_lap_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
-        let mut reader = Reader::from_reader(tcx_bytes);
-        let tc = read_training_center(&mut reader).unwrap();
-        let activity = tc
-            .activity_list
-            .unwrap()
-            .activities
-            .into_iter()
-            .next()
-            .unwrap();
-        assert_eq!(10, activity.laps.len());
-        let lap = activity.laps.into_iter().next().unwrap();
-        assert_eq!(1000.0, f64::from_str("1000.0").unwrap());
-        assert_eq!(525.0, lap.total_time_seconds);
-        assert_eq!(1000.0, lap.distance_meters);
-        assert_eq!(Some(2.330555650922987), lap.maximum_speed);
-        assert_eq!(779, lap.calories);
-        assert_eq!(Some(127), lap.average_heart_rate_bpm);
-        assert_eq!(Some(137), lap.maximum_heart_rate_bpm);
-        assert_eq!(Intensity::Active, lap.intensity);
-        assert_eq!(Some(90), lap.cadence);
-        assert_eq!(TriggerMethod::Distance, lap.trigger_method);
-        assert_eq!(true, lap.validate().is_ok());
-        assert_eq!(525, lap.track_points.len());
+    fn read_lap_without_intensity_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:16Z">
+            <TotalTimeSeconds>30</TotalTimeSeconds>
+            <DistanceMeters>200</DistanceMeters>
+            <Calories>10</Calories>
+            <TriggerMethod>Manual</TriggerMethod>
+        </Lap>"#;
         assert_eq!(
-            Some(1.9050631258222792),
-            lap.extension.as_ref().unwrap().avg_speed
+            Intensity::Active,
+            read_lap(tcx, ReadOptions::default()).unwrap().intensity
         );
-        assert_eq!(Some(210), lap.extension.as_ref().unwrap().avg_watts);
-        assert_eq!(Some(262), lap.extension.as_ref().unwrap().max_watts);
+        assert!(matches!(
+            read_lap(tcx, strict()),
+            Err(ReadError::MissingElement("Intensity"))
+        ));
     }
 
     #[test]
-    fn read_track_point_test() {
-        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+    fn read_empty_unit_id_test() {
+        let read = |unit_id: &str, options: ReadOptions| {
+            let tcx = format!(
+                "<Creator><Name>Edge</Name>{}<ProductID>3121</ProductID></Creator>",
+                unit_id
+            );
+            read_fragment_with_stats(&tcx, options, |r, _, ctx| read_device(r, b"Creator", ctx))
+                .map(|(d, stats)| (d.unit_id, stats.coercions.empty_values_defaulted))
+        };
+        let default = ReadOptions::default;
+        assert_eq!((0, 0), read("<UnitId>0</UnitId>", default()).unwrap());
+        assert_eq!((0, 1), read("<UnitId/>", default()).unwrap());
+        assert_eq!((0, 1), read("<UnitId></UnitId>", default()).unwrap());
+        assert_eq!((0, 1), read("<UnitId> </UnitId>", lenient()).unwrap());
+        assert_eq!((0, 0), read("", default()).unwrap());
+        assert_eq!((0, 0), read("<UnitId>0</UnitId>", strict()).unwrap());
+        for unit_id in &["<UnitId/>", "<UnitId></UnitId>"] {
+            assert!(matches!(
+                read(unit_id, strict()),
+                Err(ReadError::InvalidValue("UnitId", _))
+            ));
+        }
+        assert!(matches!(
+            read("<UnitId>x</UnitId>", default()),
+            Err(ReadError::ParseIntError(_))
+        ));
+    }
+
+    #[test]
+    fn read_reordered_lap_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/reordered_lap.tcx.xml");
         let mut reader = Reader::from_reader(tcx_bytes);
-        let tc = read_training_center(&mut reader).unwrap();
-        let activity = tc
-            .activity_list
-            .unwrap()
-            .activities
-            .into_iter()
-            .next()
-            .unwrap();
-        let tp = activity.laps.get(0).unwrap().track_points.get(0).unwrap();
+        let tc = read_training_center(&mut reader, &mut ReadContext::default()).unwrap();
+        let activity = &tc.activity_list.unwrap().activities[0];
+        let lap = &activity.laps[0];
+        assert_eq!(2, lap.track_points.len());
+        assert_eq!(Some(124), lap.track_points[1].heart_rate_bpm);
+        assert_eq!(Some("Track first"), lap.notes.as_deref());
+        assert_eq!(TriggerMethod::Distance, lap.trigger_method);
+        assert_eq!(Intensity::Resting, lap.intensity);
+        assert_eq!(Some(124), lap.maximum_heart_rate_bpm);
+        assert_eq!(Some(117), lap.average_heart_rate_bpm);
+        assert_eq!(2, lap.calories);
+        assert_eq!(Some(3.4), lap.maximum_speed);
+        assert_eq!(32.5, lap.distance_meters);
+        assert_eq!(10.0, lap.total_time_seconds);
+    }
+
+    #[test]
+    fn read_quirks_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/quirks.tcx.xml");
+        let read = |options| {
+            let mut reader = Reader::from_reader(tcx_bytes);
+            read_training_center(&mut reader, &mut ReadContext::new(options))
+        };
+        let tc = read(ReadOptions::default()).unwrap();
+        let activity = &tc.activity_list.unwrap().activities[0];
+        let lap = &activity.laps[0];
+        assert_eq!(activity.id, lap.start_time);
+        assert_eq!(Intensity::Active, lap.intensity);
+        match &activity.creator {
+            Some(SourceType::Device(device)) => {
+                assert_eq!(0, device.unit_id);
+                assert_eq!(3113, device.product_id);
+            }
+            creator => panic!("unexpected creator {:?}", creator),
+        }
+        match tc.author {
+            Some(SourceType::Application(application)) => {
+                assert_eq!("eng", application.lang_id);
+                assert_eq!("", application.part_number);
+            }
+            author => panic!("unexpected author {:?}", author),
+        }
+        assert!(read(strict()).is_err());
+    }
+
+    #[test]
+    fn read_step_test() {
+        let tcx = r#"<Step xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Step_t">
+            <StepId>1</StepId>
+            <Name>
+                Warmup
+            </Name>
+            <Duration xsi:type="Time_t"><Seconds>600</Seconds></Duration>
+            <Intensity>Active</Intensity>
+            <Target xsi:type="None_t"/>
+        </Step>"#;
+        let step = read_fragment(tcx, ReadOptions::default(), |r, e, ctx| {
+            read_step(r, b"Step", e, ctx)
+        })
+        .unwrap();
         assert_eq!(
-            DateTime::<Utc>::from_utc(
-                NaiveDate::from_ymd_opt(2020, 12, 28)
-                    .unwrap()
-                    .and_hms_milli_opt(13, 36, 17, 453)
-                    .unwrap(),
-                Utc
-            ),
-            tp.time
+            StepType::Step(Step {
+                step_id: Some(1),
+                name: Some("Warmup".to_string()),
+                duration: Some(Duration::Time(600)),
+                intensity: Some(Intensity::Active),
+                target: Some(Target::None),
+            }),
+            step
         );
+    }
+
+    #[test]
+    fn read_course_track_point_without_time_test() {
+        let xml = r#"<Course>
+            <Name>Route</Name>
+            <Track>
+                <Trackpoint>
+                    <Position>
+                        <LatitudeDegrees>51.752415</LatitudeDegrees>
+                        <LongitudeDegrees>39.18763</LongitudeDegrees>
+                    </Position>
+                </Trackpoint>
+            </Track>
+        </Course>"#;
+        let mut reader = Reader::from_str(xml);
+        reader.read_event_into(&mut Vec::new()).unwrap();
+        let course = read_course(&mut reader, b"Course", &mut ReadContext::default()).unwrap();
+        let tp = &course.track_points.unwrap()[0];
+        assert_eq!(None, tp.time);
         assert_eq!(
             Some(Position {
                 latitude_degrees: 51.752415,
@@ -926,20 +3960,110 @@ mod tests {
             }),
             tp.position
         );
-        assert_eq!(Some(178.615), tp.altitude_meters);
-        assert_eq!(Some(0.0), tp.distance_meters);
-        assert_eq!(Some(68), tp.heart_rate_bpm);
-        assert_eq!(Some(0), tp.cadence);
-        assert_eq!(Some(SensorState::Present), tp.sensor_state);
-        assert_eq!(Some(98), tp.extension.as_ref().unwrap().watts);
-        let mut tp_count = 0;
-        for l in &activity.laps {
-            tp_count += l.track_points.len();
-        }
-        assert_eq!(4400, tp_count)
     }
 
     #[test]
+    fn read_multi_sport_session_notes_test() {
+        let tcx = r#"<TrainingCenterDatabase>
+            <Activities>
+                <MultiSportSession>
+                    <Id>2021-07-04T07:00:00Z</Id>
+                    <FirstSport>
+                        <Activity Sport="Other">
+                            <Id>2021-07-04T07:00:00Z</Id>
+                            <Notes>Swim</Notes>
+                        </Activity>
+                    </FirstSport>
+                    <Notes>Sprint triathlon</Notes>
+                </MultiSportSession>
+                <MultiSportSession>
+                    <Id>2021-07-11T07:00:00Z</Id>
+                </MultiSportSession>
+            </Activities>
+        </TrainingCenterDatabase>"#;
+        let sessions = crate::read_str(tcx)
+            .unwrap()
+            .activity_list
+            .unwrap()
+            .multi_sport_sessions;
+        assert_eq!(2, sessions.len());
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2021, 7, 4, 7, 0, 0).unwrap()),
+            sessions[0].id
+        );
+        assert_eq!(Some("Sprint triathlon"), sessions[0].notes.as_deref());
+        assert_eq!(None, sessions[1].notes);
+    }
+
+    #[test]
+    fn read_long_track_point_extension_name_test() {
+        let tcx = r#"<Trackpoint>
+            <Time>2021-07-04T07:00:00Z</Time>
+            <Extensions>
+                <ns3:ActivityTrackpointExtension xmlns:ns3="http://www.garmin.com/xmlschemas/ActivityExtension/v2">
+                    <ns3:Speed>3.5</ns3:Speed>
+                    <ns3:Watts>210</ns3:Watts>
+                </ns3:ActivityTrackpointExtension>
+            </Extensions>
+        </Trackpoint>"#;
+        let tp = read_fragment(tcx, ReadOptions::default(), |r, _, ctx| {
+            read_track_point(r, b"Trackpoint", ctx)
+        })
+        .unwrap();
+        let extension = tp.extension.unwrap();
+        assert_eq!(Some(3.5), extension.speed);
+        assert_eq!(Some(210), extension.watts);
+    }
+
+    #[test]
+    fn read_multi_sport_session_activities_test() {
+        let biking = include_str!("../test_resources/biking.tcx.xml");
+        let tcx = biking
+            .replacen(
+                "</Activity>",
+                "</Activity></NextSport></MultiSportSession>",
+                1,
+            )
+            .replacen(
+                r#"<Activity Sport="Biking">"#,
+                r#"<MultiSportSession>
+                    <Id>2023-03-26T11:00:00Z</Id>
+                    <FirstSport>
+                        <Activity Sport="Running">
+                            <Id>2023-03-26T11:00:00Z</Id>
+                        </Activity>
+                    </FirstSport>
+                    <NextSport>
+                        <Transition StartTime="2023-03-26T11:42:00Z">
+                            <TotalTimeSeconds>124.0</TotalTimeSeconds>
+                            <DistanceMeters>150.0</DistanceMeters>
+                            <Calories>8</Calories>
+                            <Intensity>Active</Intensity>
+                            <TriggerMethod>Manual</TriggerMethod>
+                        </Transition>
+                        <Activity Sport="Biking">"#,
+                1,
+            );
+        let list = crate::read_str(&tcx).unwrap().activity_list.unwrap();
+        assert!(list.activities.is_empty());
+        let sports = list.multi_sport_sessions[0].sports.as_ref().unwrap();
+        assert_eq!(2, sports.len());
+        assert_eq!(None, sports[0].transition);
+        assert_eq!(Sport::Running, sports[0].activity.as_ref().unwrap().sport);
+        let transition = sports[1].transition.as_ref().unwrap();
+        assert_eq!(124.0, transition.total_time_seconds);
+        assert_eq!(
+            Utc.with_ymd_and_hms(2023, 3, 26, 11, 42, 0).unwrap(),
+            transition.start_time
+        );
+
+        let standalone = crate::read_str(biking).unwrap().activity_list.unwrap();
+        let bike_leg = sports[1].activity.as_ref().unwrap();
+        assert_eq!(&standalone.activities[0], bike_leg);
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_application_validate() {
         let mut application = Application::default();
         let vr: ValidationErrors = application.validate().unwrap_err();
@@ -949,4 +4073,124 @@ mod tests {
         application.lang_id = String::from("EN");
         assert_eq!(true, application.validate().is_ok())
     }
+
+    #[test]
+    fn test_plan_validate() {
+        let tcx = r#"<Plan Type="Workout" IntervalWorkout="false">
+            <Name>Тренировка</Name>
+        </Plan>"#;
+        let mut plan = read_fragment(tcx, ReadOptions::default(), |r, e, ctx| {
+            read_plan(r, b"Plan", e, ctx)
+        })
+        .unwrap()
+        .0;
+        let name = plan.name.as_ref().unwrap();
+        assert_eq!(10, name.chars().count());
+        assert_eq!(20, name.len());
+        let vr: ValidationErrors = plan.validate().unwrap_err();
+        assert!(vr.field_errors().contains_key("name"));
+        plan.name = Some(String::from("Тренир"));
+        assert!(plan.validate().is_ok());
+        plan.name = Some(String::new());
+        assert!(plan.validate().is_err());
+    }
+
+    #[test]
+    fn read_distance_unit_test() {
+        let tcx = r#"<Lap StartTime="2020-12-28T13:36:17Z">
+            <DistanceMeters unit="km">1.0</DistanceMeters>
+            <Track>
+                <Trackpoint>
+                    <Time>2020-12-28T13:36:17Z</Time>
+                    <DistanceMeters unit="km">0.25</DistanceMeters>
+                </Trackpoint>
+            </Track>
+        </Lap>"#;
+        let lap = read_lap(tcx, ReadOptions::default()).unwrap();
+        assert_eq!(1.0, lap.distance_meters);
+        assert_eq!(Some(0.25), lap.track_points[0].distance_meters);
+
+        let (lap, stats) = read_lap_with_stats(tcx, lenient()).unwrap();
+        assert_eq!(1000.0, lap.distance_meters);
+        assert_eq!(Some(250.0), lap.track_points[0].distance_meters);
+        assert_eq!(2, stats.coercions.distance_unit_converted);
+    }
+
+    #[test]
+    fn read_cadence_sensor_test() {
+        let read_tpx = |tcx| {
+            read_fragment(tcx, ReadOptions::default(), |r, e, ctx| {
+                read_activity_track_point_extension(r, b"TPX", e, ctx)
+            })
+            .unwrap()
+        };
+        let tpx = read_tpx(r#"<TPX><CadenceSensor>Footpod</CadenceSensor></TPX>"#);
+        assert_eq!(Some(CadenceSensorType::Footpod), tpx.cadence_sensor);
+        let tpx = read_tpx(r#"<TPX CadenceSensor="Bike"><Watts>180</Watts></TPX>"#);
+        assert_eq!(Some(CadenceSensorType::Bike), tpx.cadence_sensor);
+        assert_eq!(Some(180), tpx.watts);
+    }
+
+    #[test]
+    fn read_tpx_any_order_test() {
+        let tcx = r#"<TPX xmlns="http://www.garmin.com/xmlschemas/ActivityExtension/v2">
+            <Watts>215</Watts>
+            <RunCadence>88</RunCadence>
+            <Speed>3.5</Speed>
+        </TPX>"#;
+        let tpx = read_fragment(tcx, ReadOptions::default(), |r, e, ctx| {
+            read_activity_track_point_extension(r, b"TPX", e, ctx)
+        })
+        .unwrap();
+        assert_eq!(
+            ActivityTrackPointExtension {
+                speed: Some(3.5),
+                run_cadence: Some(88),
+                watts: Some(215),
+                cadence_sensor: None,
+            },
+            tpx
+        );
+    }
+
+    #[test]
+    fn read_training_test() {
+        let plan = Plan {
+            name: Some(String::from("Tempo")),
+            training_type: TrainingType::Workout,
+            interval_workout: false,
+        };
+        let quick_workout = QuickWorkout {
+            total_time_seconds: 1800.0,
+            distance_meters: 6000.0,
+        };
+        let quick_workout_xml = r#"<QuickWorkoutResults>
+                <TotalTimeSeconds>1800</TotalTimeSeconds>
+                <DistanceMeters>6000</DistanceMeters>
+            </QuickWorkoutResults>"#;
+        let plan_xml = r#"<Plan Type="Workout" IntervalWorkout="false"><Name>Tempo</Name>"#;
+        for tcx in [
+            format!(
+                "<Training>{}</Plan>{}</Training>",
+                plan_xml, quick_workout_xml
+            ),
+            format!(
+                "<Training>{}{}</Plan></Training>",
+                quick_workout_xml, plan_xml
+            ),
+            format!(
+                "<Training>{}{}</Plan></Training>",
+                plan_xml, quick_workout_xml
+            ),
+        ] {
+            let mut reader = Reader::from_reader(tcx.as_bytes());
+            let training =
+                read_training(&mut reader, b"Training", &mut ReadContext::default()).unwrap();
+            assert_eq!(Some(&plan), training.plan.as_ref());
+            assert_eq!(
+                Some(&quick_workout),
+                training.quick_workout_results.as_ref()
+            );
+        }
+    }
 }
@@ -0,0 +1,685 @@
+use chrono::{DateTime, Utc};
+
+use crate::metrics::{horizontal_distance, seconds_between};
+use crate::types::*;
+
+impl Activity {
+    /// Collapses adjacent track points of a lap sharing the same timestamp, keeping
+    /// the last one of them as later samples usually carry more complete data.
+    /// Track points without time are kept.
+    pub fn dedup_by_time(&mut self) {
+        for lap in &mut self.laps {
+            let mut track_points: Vec<TrackPoint> = Vec::with_capacity(lap.track_points.len());
+            for tp in lap.track_points.drain(..) {
+                match track_points.last_mut() {
+                    Some(last) if tp.time.is_some() && last.time == tp.time => *last = tp,
+                    _ => track_points.push(tp),
+                }
+            }
+            lap.track_points = track_points;
+        }
+    }
+
+    /// Splits the activity into the part recorded before `time` and the part from `time` on.
+    ///
+    /// A lap with track points on both sides of `time` is split in two with `total_time_seconds`
+    /// and `distance_meters` recomputed from the track points of each half, other values of the
+    /// lap are copied to both halves. Laps without track points go by their `start_time`,
+    /// track points without time go to the second part. The second activity starts at its
+    /// first track point with time, or at `time` if it has none.
+    pub fn split_at_time(&self, time: DateTime<Utc>) -> (Activity, Activity) {
+        let mut before = self.without_laps(self.id);
+        let mut after = self.without_laps(time);
+        for lap in &self.laps {
+            if lap.track_points.is_empty() {
+                if lap.start_time < time {
+                    before.laps.push(lap.clone());
+                } else {
+                    after.laps.push(lap.clone());
+                }
+                continue;
+            }
+            let (lap_before, lap_after): (Vec<TrackPoint>, Vec<TrackPoint>) = lap
+                .track_points
+                .iter()
+                .cloned()
+                .partition(|tp| tp.time.is_some_and(|t| t < time));
+            match (lap_before.is_empty(), lap_after.is_empty()) {
+                (false, true) => before.laps.push(lap.clone()),
+                (true, false) => after.laps.push(lap.clone()),
+                _ => {
+                    let after_start = lap_after.iter().find_map(|tp| tp.time).unwrap_or(time);
+                    before.laps.push(lap_part(lap, lap.start_time, lap_before));
+                    after.laps.push(lap_part(lap, after_start, lap_after));
+                }
+            }
+        }
+        let after_start = after.track_points().find_map(|tp| tp.time);
+        after.id = after_start.unwrap_or(time);
+        (before, after)
+    }
+
+    /// Track points of all laps split into contiguous segments wherever more than `max_gap_secs`
+    /// seconds pass between two track points, e.g. a pause or a GPS dropout. Track points
+    /// without time stay in the segment of the preceding track point.
+    pub fn split_on_time_gaps(&self, max_gap_secs: f64) -> Vec<Vec<TrackPoint>> {
+        let mut segments: Vec<Vec<TrackPoint>> = Vec::new();
+        let mut previous_time: Option<DateTime<Utc>> = None;
+        for tp in self.track_points() {
+            let gap = match (previous_time, tp.time) {
+                (Some(previous), Some(time)) => {
+                    (time - previous).num_milliseconds() as f64 / 1000.0 > max_gap_secs
+                }
+                _ => false,
+            };
+            match segments.last_mut() {
+                Some(segment) if !gap => segment.push(tp.clone()),
+                _ => segments.push(vec![tp.clone()]),
+            }
+            previous_time = tp.time.or(previous_time);
+        }
+        segments
+    }
+
+    /// Fills `distance_meters` of the track points with the cumulative great-circle distance
+    /// between their positions. Existing distances are replaced if `overwrite` is set, otherwise
+    /// they're kept and the missing ones continue from the closest preceding distance.
+    pub fn recompute_distances(&mut self, overwrite: bool) {
+        let mut distance = 0.0;
+        let mut previous: Option<Position> = None;
+        for tp in self.laps.iter_mut().flat_map(|l| l.track_points.iter_mut()) {
+            if let (Some(from), Some(to)) = (&previous, &tp.position) {
+                distance += from.distance_to(to);
+            }
+            match tp.distance_meters {
+                Some(d) if !overwrite => distance = d,
+                _ => tp.distance_meters = Some(distance),
+            }
+            if tp.position.is_some() {
+                previous = tp.position.clone();
+            }
+        }
+    }
+
+    /// Raises every `distance_meters` lower than the one of a preceding track point to that
+    /// distance, so the distances never decrease, e.g. after GPS noise. Track points without
+    /// distance are left as they are.
+    pub fn fix_distance_monotonicity(&mut self) {
+        let mut max_distance: Option<f64> = None;
+        for tp in self.laps.iter_mut().flat_map(|l| l.track_points.iter_mut()) {
+            if let Some(distance) = &mut tp.distance_meters {
+                match max_distance {
+                    Some(max) if *distance < max => *distance = max,
+                    _ => max_distance = Some(*distance),
+                }
+            }
+        }
+    }
+
+    /// Strips the data identifying the athlete or the device: positions of the track points,
+    /// the serial number (`unit_id`) of the creator device and the notes of the activity and laps.
+    pub fn anonymize(&mut self) {
+        self.notes = None;
+        if let Some(SourceType::Device(device)) = &mut self.creator {
+            device.unit_id = 0;
+        }
+        for lap in &mut self.laps {
+            lap.notes = None;
+            for tp in &mut lap.track_points {
+                tp.position = None;
+            }
+        }
+    }
+
+    /// `n` track points picked at even intervals over all laps, always including the first
+    /// and the last one. All track points are returned if there are no more than `n`.
+    pub fn downsample_to_count(&self, n: usize) -> Vec<TrackPoint> {
+        let points: Vec<&TrackPoint> = self.track_points().collect();
+        if points.len() <= n {
+            return points.into_iter().cloned().collect();
+        }
+        match n {
+            0 => Vec::new(),
+            1 => vec![points[0].clone()],
+            _ => {
+                let step = (points.len() - 1) as f64 / (n - 1) as f64;
+                (0..n)
+                    .map(|i| points[(i as f64 * step).round() as usize].clone())
+                    .collect()
+            }
+        }
+    }
+
+    /// Rounds the latitude and longitude of the track points to `decimals` decimal places,
+    /// e.g. 5 decimals keep a precision of about a meter.
+    pub fn round_coordinates(&mut self, decimals: u32) {
+        let factor = 10_f64.powi(decimals as i32);
+        let round = |degrees: f64| (degrees * factor).round() / factor;
+        for tp in self.laps.iter_mut().flat_map(|l| l.track_points.iter_mut()) {
+            if let Some(position) = &mut tp.position {
+                position.latitude_degrees = round(position.latitude_degrees);
+                position.longitude_degrees = round(position.longitude_degrees);
+            }
+        }
+    }
+
+    /// Overwrites the altitude of each track point with a position by the result of `lookup`,
+    /// e.g. an elevation from a digital elevation model. Altitudes are kept where `lookup`
+    /// returns `None`.
+    pub fn apply_elevation(&mut self, lookup: impl Fn(&Position) -> Option<f64>) {
+        for tp in self.laps.iter_mut().flat_map(|l| l.track_points.iter_mut()) {
+            if let Some(altitude) = tp.position.as_ref().and_then(&lookup) {
+                tp.altitude_meters = Some(altitude);
+            }
+        }
+    }
+
+    /// Removes the track points recorded before the first and after the last movement
+    /// at `speed_threshold_mps` or faster, stops in between are kept. The speed comes from
+    /// the `Speed` extension, otherwise from the distance to the previous track point.
+    /// Laps left without track points are removed. Nothing changes if the activity never moves.
+    pub fn trim_stationary(&mut self, speed_threshold_mps: f64) {
+        let points: Vec<&TrackPoint> = self.track_points().collect();
+        let moving: Vec<bool> = (0..points.len())
+            .map(|i| {
+                let speed = points[i].speed();
+                let (from, to) = if i == 0 { (0, 1) } else { (i - 1, i) };
+                speed
+                    .or_else(|| {
+                        points
+                            .get(to)
+                            .and_then(|to| speed_between(points[from], to))
+                    })
+                    .is_some_and(|s| s >= speed_threshold_mps)
+            })
+            .collect();
+        let (first, last) = match (
+            moving.iter().position(|&m| m),
+            moving.iter().rposition(|&m| m),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return,
+        };
+        let mut index = 0;
+        let mut trimmed_away = Vec::with_capacity(self.laps.len());
+        for lap in &mut self.laps {
+            let len = lap.track_points.len();
+            let to = (last + 1).saturating_sub(index).min(len);
+            let from = first.saturating_sub(index).min(to);
+            index += len;
+            trimmed_away.push(len > 0 && from == to);
+            lap.track_points.truncate(to);
+            lap.track_points.drain(..from);
+        }
+        let mut trimmed_away = trimmed_away.into_iter();
+        self.laps.retain(|_| !trimmed_away.next().unwrap_or(false));
+    }
+
+    fn without_laps(&self, id: DateTime<Utc>) -> Activity {
+        Activity {
+            id,
+            laps: Vec::new(),
+            notes: self.notes.clone(),
+            training: self.training.clone(),
+            creator: self.creator.clone(),
+            sport: self.sport.clone(),
+            extensions: self.extensions.clone(),
+        }
+    }
+}
+
+impl ActivityList {
+    /// Sorts the activities by `id` and keeps one activity per `id`, the one with the most
+    /// track points, or the first of them on a tie.
+    pub fn sort_and_dedup_by_id(&mut self) {
+        self.activities.sort_by_key(|a| a.id);
+        self.activities.dedup_by(|later, kept| {
+            if later.id != kept.id {
+                return false;
+            }
+            if later.track_points().count() > kept.track_points().count() {
+                std::mem::swap(later, kept);
+            }
+            true
+        });
+    }
+}
+
+impl TrainingCenterDatabase {
+    /// Appends the activities and multi-sport sessions of `other` to the activity list, except
+    /// the ones with an `id` already in the list. The other parts, including the author, are
+    /// kept and only taken from `other` if missing.
+    pub fn merge(&mut self, other: TrainingCenterDatabase) {
+        if let Some(other_list) = other.activity_list {
+            let list = self.activity_list.get_or_insert_with(ActivityList::default);
+            for activity in other_list.activities {
+                if list.find_by_id(&activity.id).is_none() {
+                    list.activities.push(activity);
+                }
+            }
+            for session in other_list.multi_sport_sessions {
+                let duplicate = session.id.is_some()
+                    && list.multi_sport_sessions.iter().any(|s| s.id == session.id);
+                if !duplicate {
+                    list.multi_sport_sessions.push(session);
+                }
+            }
+        }
+        self.folders = self.folders.take().or(other.folders);
+        self.workout_list = self.workout_list.take().or(other.workout_list);
+        self.course_list = self.course_list.take().or(other.course_list);
+        self.author = self.author.take().or(other.author);
+    }
+}
+
+/// Speed in meters per second between two track points, `None` without distance or time passed.
+fn speed_between(from: &TrackPoint, to: &TrackPoint) -> Option<f64> {
+    let seconds = seconds_between(from, to)?;
+    if seconds <= 0.0 {
+        return None;
+    }
+    Some(horizontal_distance(from, to)? / seconds)
+}
+
+/// Part of the `lap` consisting of the `track_points` with time and distance taken from them.
+fn lap_part(
+    lap: &ActivityLap,
+    start_time: DateTime<Utc>,
+    track_points: Vec<TrackPoint>,
+) -> ActivityLap {
+    let seconds = match (track_points.first(), track_points.last()) {
+        (Some(first), Some(last)) => seconds_between(first, last).unwrap_or(0.0),
+        _ => 0.0,
+    };
+    let meters = track_points
+        .windows(2)
+        .filter_map(|w| horizontal_distance(&w[0], &w[1]))
+        .sum();
+    ActivityLap {
+        total_time_seconds: seconds,
+        distance_meters: meters,
+        maximum_speed: lap.maximum_speed,
+        calories: lap.calories,
+        average_heart_rate_bpm: lap.average_heart_rate_bpm,
+        maximum_heart_rate_bpm: lap.maximum_heart_rate_bpm,
+        average_heart_rate_percent: lap.average_heart_rate_percent,
+        maximum_heart_rate_percent: lap.maximum_heart_rate_percent,
+        intensity: lap.intensity.clone(),
+        cadence: lap.cadence,
+        trigger_method: lap.trigger_method.clone(),
+        track_points,
+        notes: lap.notes.clone(),
+        start_time,
+        extension: lap.extension.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+
+    fn track_point(seconds: i64) -> TrackPoint {
+        TrackPoint {
+            time: Some(
+                Utc.with_ymd_and_hms(2020, 12, 28, 13, 0, 0).unwrap() + Duration::seconds(seconds),
+            ),
+            ..TrackPoint::default()
+        }
+    }
+
+    fn activity(track_points: Vec<TrackPoint>) -> Activity {
+        Activity {
+            laps: vec![ActivityLap {
+                track_points,
+                ..ActivityLap::default()
+            }],
+            ..Activity::default()
+        }
+    }
+
+    #[test]
+    fn dedup_by_time_test() {
+        let mut duplicate = track_point(1);
+        duplicate.heart_rate_bpm = Some(120);
+        let mut activity = activity(vec![
+            track_point(0),
+            track_point(1),
+            duplicate,
+            track_point(2),
+        ]);
+        activity.dedup_by_time();
+        let track_points = &activity.laps[0].track_points;
+        assert_eq!(3, track_points.len());
+        assert_eq!(Some(120), track_points[1].heart_rate_bpm);
+        assert!(track_points.windows(2).all(|w| w[0].time < w[1].time));
+    }
+
+    #[test]
+    fn split_at_time_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        let lap = &activity.laps[3];
+        let split_point = &lap.track_points[lap.track_points.len() / 2];
+        let (before, after) = activity.split_at_time(split_point.time.unwrap());
+
+        assert_eq!(
+            activity.track_points().count(),
+            before.track_points().count() + after.track_points().count()
+        );
+        assert_eq!(
+            activity.laps.len() + 1,
+            before.laps.len() + after.laps.len()
+        );
+        assert_eq!(4, before.laps.len());
+        assert_eq!(activity.id, before.id);
+        assert_eq!(split_point.time, Some(after.id));
+        assert!(before.track_points().all(|tp| tp.time < split_point.time));
+        assert!(after.track_points().all(|tp| tp.time >= split_point.time));
+
+        let (first_half, second_half) = (&before.laps[3], &after.laps[0]);
+        assert_eq!(split_point.time, Some(second_half.start_time));
+        assert!(
+            first_half.total_time_seconds + second_half.total_time_seconds
+                <= lap.total_time_seconds
+        );
+        assert!(first_half.distance_meters > 0.0 && second_half.distance_meters > 0.0);
+        assert!(first_half.distance_meters + second_half.distance_meters <= lap.distance_meters);
+    }
+
+    #[test]
+    fn recompute_distances_test() {
+        let points: Vec<TrackPoint> = (0..4)
+            .map(|i| TrackPoint {
+                position: Some(Position {
+                    latitude_degrees: 51.7524 + i as f64 * 0.0001,
+                    longitude_degrees: 39.18763,
+                }),
+                ..track_point(i)
+            })
+            .collect();
+        let mut activity = activity(points);
+        activity.laps[0].track_points[2].position = None;
+        activity.recompute_distances(false);
+        let distances: Vec<f64> = activity
+            .track_points()
+            .map(|tp| tp.distance_meters.unwrap())
+            .collect();
+        assert_eq!(0.0, distances[0]);
+        assert!(distances[1] > 10.0 && distances[1] < 12.0);
+        assert_eq!(distances[1], distances[2]);
+        assert!(distances[3] > 2.0 * distances[1]);
+
+        activity.laps[0].track_points[1].distance_meters = None;
+        activity.laps[0].track_points[2].distance_meters = Some(100.0);
+        activity.laps[0].track_points[3].distance_meters = None;
+        activity.recompute_distances(false);
+        assert_eq!(
+            Some(distances[1]),
+            activity.laps[0].track_points[1].distance_meters
+        );
+        assert_eq!(
+            Some(100.0),
+            activity.laps[0].track_points[2].distance_meters
+        );
+        assert!(activity.laps[0].track_points[3].distance_meters.unwrap() > 100.0);
+
+        activity.recompute_distances(true);
+        assert_eq!(
+            Some(distances[2]),
+            activity.laps[0].track_points[2].distance_meters
+        );
+    }
+
+    #[test]
+    fn trim_stationary_test() {
+        let distances = [0.0, 0.5, 1.0, 1.0, 5.0, 10.0, 10.5, 15.0, 20.0, 20.2, 20.2];
+        let points: Vec<TrackPoint> = distances
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| TrackPoint {
+                distance_meters: Some(d),
+                ..track_point(i as i64)
+            })
+            .collect();
+        let mut padded = activity(points[..3].to_vec());
+        for lap_points in [&points[3..9], &points[9..]] {
+            padded.laps.push(ActivityLap {
+                track_points: lap_points.to_vec(),
+                ..ActivityLap::default()
+            });
+        }
+        padded.trim_stationary(1.0);
+        let kept: Vec<f64> = padded
+            .track_points()
+            .map(|tp| tp.distance_meters.unwrap())
+            .collect();
+        // the stop at 10.0..10.5 in the middle is kept
+        assert_eq!(vec![5.0, 10.0, 10.5, 15.0, 20.0], kept);
+        assert_eq!(1, padded.laps.len());
+
+        let mut stationary = activity(vec![track_point(0), track_point(1)]);
+        stationary.trim_stationary(1.0);
+        assert_eq!(2, stationary.track_points().count());
+    }
+
+    #[test]
+    fn fix_distance_monotonicity_test() {
+        let with_distance = |seconds, distance| TrackPoint {
+            distance_meters: distance,
+            ..track_point(seconds)
+        };
+        let mut activity = activity(vec![
+            with_distance(0, Some(0.0)),
+            with_distance(1, Some(10.0)),
+            with_distance(2, Some(8.5)),
+            with_distance(3, None),
+            with_distance(4, Some(9.0)),
+            with_distance(5, Some(20.0)),
+        ]);
+        activity.laps.push(ActivityLap {
+            track_points: vec![with_distance(6, Some(19.0)), with_distance(7, Some(30.0))],
+            ..ActivityLap::default()
+        });
+        activity.fix_distance_monotonicity();
+        let distances: Vec<Option<f64>> = activity
+            .track_points()
+            .map(|tp| tp.distance_meters)
+            .collect();
+        assert_eq!(
+            vec![
+                Some(0.0),
+                Some(10.0),
+                Some(10.0),
+                None,
+                Some(10.0),
+                Some(20.0),
+                Some(20.0),
+                Some(30.0)
+            ],
+            distances
+        );
+    }
+
+    #[test]
+    fn merge_test() {
+        let database = |seconds, device: &str| TrainingCenterDatabase {
+            activity_list: Some(ActivityList {
+                activities: vec![Activity {
+                    id: track_point(seconds).time.unwrap(),
+                    ..activity(vec![track_point(seconds)])
+                }],
+                ..ActivityList::default()
+            }),
+            author: Some(SourceType::Device(Device {
+                name: device.to_string(),
+                ..Device::default()
+            })),
+            ..TrainingCenterDatabase::default()
+        };
+        let mut merged = database(0, "first");
+        merged.merge(database(3600, "second"));
+        let ids: Vec<DateTime<Utc>> = merged
+            .activity_list
+            .as_ref()
+            .unwrap()
+            .activities
+            .iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(
+            vec![
+                track_point(0).time.unwrap(),
+                track_point(3600).time.unwrap()
+            ],
+            ids
+        );
+        match &merged.author {
+            Some(SourceType::Device(device)) => assert_eq!("first", device.name),
+            author => panic!("unexpected author {:?}", author),
+        }
+
+        merged.merge(database(0, "third"));
+        assert_eq!(2, merged.activity_list.as_ref().unwrap().activities.len());
+
+        let mut empty = TrainingCenterDatabase::default();
+        empty.merge(database(0, "fourth"));
+        assert_eq!(1, empty.activity_list.unwrap().activities.len());
+        assert!(empty.author.is_some());
+    }
+
+    #[test]
+    fn downsample_to_count_test() {
+        let activity = activity((0..525).map(track_point).collect());
+        let sampled = activity.downsample_to_count(50);
+        assert_eq!(50, sampled.len());
+        assert_eq!(track_point(0).time, sampled[0].time);
+        assert_eq!(track_point(524).time, sampled[49].time);
+        assert!(sampled.windows(2).all(|w| w[0].time < w[1].time));
+        assert_eq!(525, activity.downsample_to_count(600).len());
+        assert_eq!(1, activity.downsample_to_count(1).len());
+        assert!(activity.downsample_to_count(0).is_empty());
+    }
+
+    #[test]
+    fn split_on_time_gaps_test() {
+        let activity = activity(vec![
+            track_point(0),
+            track_point(5),
+            track_point(10),
+            track_point(610),
+            TrackPoint::default(),
+            track_point(615),
+        ]);
+        let segments = activity.split_on_time_gaps(300.0);
+        let lengths: Vec<usize> = segments.iter().map(|s| s.len()).collect();
+        assert_eq!(vec![3, 3], lengths);
+        assert_eq!(track_point(610).time, segments[1][0].time);
+        assert_eq!(1, activity.split_on_time_gaps(600.0).len());
+        assert!(Activity::default().split_on_time_gaps(300.0).is_empty());
+    }
+
+    #[test]
+    fn anonymize_test() {
+        let mut activity = activity(vec![TrackPoint {
+            position: Some(Position {
+                latitude_degrees: 51.752415,
+                longitude_degrees: 39.18763,
+            }),
+            heart_rate_bpm: Some(120),
+            ..track_point(0)
+        }]);
+        activity.notes = Some("Morning run from home".to_string());
+        activity.laps[0].notes = Some("Past the office".to_string());
+        activity.creator = Some(SourceType::Device(Device {
+            name: "Forerunner 945".to_string(),
+            unit_id: 3318234150,
+            ..Device::default()
+        }));
+        activity.anonymize();
+        assert!(activity.track_points().all(|tp| tp.position.is_none()));
+        assert_eq!(Some(120), activity.laps[0].track_points[0].heart_rate_bpm);
+        match &activity.creator {
+            Some(SourceType::Device(device)) => {
+                assert_eq!(0, device.unit_id);
+                assert_eq!("Forerunner 945", device.name);
+            }
+            creator => panic!("unexpected creator {:?}", creator),
+        }
+        assert_eq!(None, activity.notes);
+        assert_eq!(None, activity.laps[0].notes);
+    }
+
+    #[test]
+    fn round_coordinates_test() {
+        let mut activity = activity(vec![
+            TrackPoint {
+                position: Some(Position {
+                    latitude_degrees: 51.752415,
+                    longitude_degrees: -39.18763,
+                }),
+                ..track_point(0)
+            },
+            track_point(1),
+        ]);
+        activity.round_coordinates(3);
+        assert_eq!(
+            Some(Position {
+                latitude_degrees: 51.752,
+                longitude_degrees: -39.188,
+            }),
+            activity.laps[0].track_points[0].position
+        );
+        assert_eq!(None, activity.laps[0].track_points[1].position);
+    }
+
+    #[test]
+    fn sort_and_dedup_by_id_test() {
+        let recorded = |seconds, points| Activity {
+            id: track_point(seconds).time.unwrap(),
+            ..activity((0..points).map(track_point).collect())
+        };
+        let mut list = ActivityList {
+            activities: vec![
+                recorded(60, 1),
+                recorded(0, 2),
+                recorded(60, 3),
+                recorded(60, 2),
+            ],
+            ..ActivityList::default()
+        };
+        list.sort_and_dedup_by_id();
+        let kept: Vec<(DateTime<Utc>, usize)> = list
+            .activities
+            .iter()
+            .map(|a| (a.id, a.track_points().count()))
+            .collect();
+        assert_eq!(
+            vec![
+                (track_point(0).time.unwrap(), 2),
+                (track_point(60).time.unwrap(), 3)
+            ],
+            kept
+        );
+    }
+
+    #[test]
+    fn apply_elevation_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let mut list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        list.activities[0].apply_elevation(|_| Some(100.0));
+        assert!(list.activities[0]
+            .track_points()
+            .filter(|tp| tp.position.is_some())
+            .all(|tp| tp.altitude_meters == Some(100.0)));
+
+        let mut activity = activity(vec![TrackPoint {
+            altitude_meters: Some(50.0),
+            ..track_point(0)
+        }]);
+        activity.apply_elevation(|_| Some(100.0));
+        assert_eq!(Some(50.0), activity.laps[0].track_points[0].altitude_meters);
+    }
+}
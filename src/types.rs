@@ -2,10 +2,11 @@ use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 use chrono::DateTime;
-use chrono::{Utc};
+use chrono::TimeZone;
+use chrono::Utc;
 use regex::Regex;
 use thiserror::Error;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Error, Debug)]
 pub enum UnknownEnumValueError {
@@ -16,6 +17,8 @@ pub enum UnknownEnumValueError {
     TriggerMethod(String),
     SensorState(String),
     CadenceSensorType(String),
+    SpeedType(String),
+    CoursePointType(String),
 }
 
 impl Display for UnknownEnumValueError {
@@ -30,17 +33,31 @@ impl Display for UnknownEnumValueError {
             UnknownEnumValueError::CadenceSensorType(t) => {
                 write!(f, "unknown '{}' cadence sensor type", t)
             }
+            UnknownEnumValueError::SpeedType(t) => write!(f, "unknown '{}' speed type", t),
+            UnknownEnumValueError::CoursePointType(t) => {
+                write!(f, "unknown '{}' course point type", t)
+            }
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SourceType {
     Application(Application),
     Device(Device),
 }
 
-#[derive(Debug, PartialEq)]
+impl SourceType {
+    /// Version of the device or of the application build.
+    pub fn version(&self) -> &Version {
+        match self {
+            SourceType::Application(application) => &application.build.version,
+            SourceType::Device(device) => &device.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum BuildType {
     Internal,
     Alpha,
@@ -57,14 +74,12 @@ impl FromStr for BuildType {
             "Alpha" => Ok(BuildType::Alpha),
             "Beta" => Ok(BuildType::Beta),
             "Release" => Ok(BuildType::Release),
-            _ => {
-                return Err(UnknownEnumValueError::BuildType(s.to_string()));
-            }
+            _ => Err(UnknownEnumValueError::BuildType(s.to_string())),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CoursePointType {
     Generic,
     Summit,
@@ -84,13 +99,39 @@ pub enum CoursePointType {
     Sprint,
 }
 
-#[derive(Debug, PartialEq)]
+impl FromStr for CoursePointType {
+    type Err = UnknownEnumValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Generic" => Ok(Self::Generic),
+            "Summit" => Ok(Self::Summit),
+            "Valley" => Ok(Self::Valley),
+            "Water" => Ok(Self::Water),
+            "Food" => Ok(Self::Food),
+            "Danger" => Ok(Self::Danger),
+            "Left" => Ok(Self::Left),
+            "Right" => Ok(Self::Right),
+            "Straight" => Ok(Self::Straight),
+            "First Aid" => Ok(Self::FirstAid),
+            "4th Category" => Ok(Self::Category4),
+            "3rd Category" => Ok(Self::Category3),
+            "2nd Category" => Ok(Self::Category2),
+            "1st Category" => Ok(Self::Category1),
+            "Hors Category" => Ok(Self::HorsCategory),
+            "Sprint" => Ok(Self::Sprint),
+            _ => Err(UnknownEnumValueError::CoursePointType(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum StepType {
     Step(Step),
     Repeat(Repeat),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Target {
     Speed(Zone),
     HeartRate(Zone),
@@ -98,7 +139,7 @@ pub enum Target {
     None,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Zone {
     PredefinedSpeedZone(u8),
     CustomSpeedZone(CustomSpeedZone),
@@ -106,13 +147,25 @@ pub enum Zone {
     CustomHeartRateZone(CustomHeartRateZone),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SpeedType {
     Pace,
     Speed,
 }
 
-#[derive(Debug, PartialEq)]
+impl FromStr for SpeedType {
+    type Err = UnknownEnumValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pace" => Ok(Self::Pace),
+            "Speed" => Ok(Self::Speed),
+            _ => Err(UnknownEnumValueError::SpeedType(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Duration {
     Time(u16),
     Distance(u16),
@@ -121,18 +174,13 @@ pub enum Duration {
     CaloriesBurned(u16),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum TrainingType {
+    #[default]
     Workout,
     Course,
 }
 
-impl Default for TrainingType {
-    fn default() -> Self {
-        TrainingType::Workout
-    }
-}
-
 impl FromStr for TrainingType {
     type Err = UnknownEnumValueError;
 
@@ -140,25 +188,18 @@ impl FromStr for TrainingType {
         match s {
             "Workout" => Ok(TrainingType::Workout),
             "Course" => Ok(TrainingType::Course),
-            _ => {
-                return Err(UnknownEnumValueError::TrainingType(s.to_string()));
-            }
+            _ => Err(UnknownEnumValueError::TrainingType(s.to_string())),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum SensorState {
+    #[default]
     Present,
     Absent,
 }
 
-impl Default for SensorState {
-    fn default() -> Self {
-        Self::Present
-    }
-}
-
 impl FromStr for SensorState {
     type Err = UnknownEnumValueError;
 
@@ -166,14 +207,12 @@ impl FromStr for SensorState {
         match s {
             "Present" => Ok(Self::Present),
             "Absent" => Ok(Self::Absent),
-            _ => {
-                return Err(UnknownEnumValueError::SensorState(s.to_string()));
-            }
+            _ => Err(UnknownEnumValueError::SensorState(s.to_string())),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Intensity {
     Active,
     Resting,
@@ -186,14 +225,12 @@ impl FromStr for Intensity {
         match s {
             "Active" => Ok(Self::Active),
             "Resting" => Ok(Self::Resting),
-            _ => {
-                return Err(UnknownEnumValueError::Intensity(s.to_string()));
-            }
+            _ => Err(UnknownEnumValueError::Intensity(s.to_string())),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TriggerMethod {
     Manual,
     Distance,
@@ -211,15 +248,14 @@ impl FromStr for TriggerMethod {
             "Distance" => Ok(Self::Distance),
             "Location" => Ok(Self::Location),
             "Time" => Ok(Self::Time),
-            "HeartRate" => Ok(Self::HeartRate),
-            _ => {
-                return Err(UnknownEnumValueError::TriggerMethod(s.to_string()));
-            }
+            // some exporters write the heart rate trigger with a space
+            "HeartRate" | "Heart Rate" => Ok(Self::HeartRate),
+            _ => Err(UnknownEnumValueError::TriggerMethod(s.to_string())),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Sport {
     Running,
     Biking,
@@ -234,9 +270,7 @@ impl FromStr for Sport {
             "Running" => Ok(Sport::Running),
             "Biking" => Ok(Sport::Biking),
             "Other" => Ok(Sport::Other),
-            _ => {
-                return Err(UnknownEnumValueError::Sport(s.to_string()));
-            }
+            _ => Err(UnknownEnumValueError::Sport(s.to_string())),
         }
     }
 }
@@ -247,7 +281,7 @@ lazy_static! {
 }
 
 /// Identifies a PC software application.
-#[derive(Default, Debug, PartialEq, Validate)]
+#[derive(Default, Debug, Clone, PartialEq, Validate)]
 pub struct Application {
     pub name: String,
     pub build: Build,
@@ -262,7 +296,7 @@ pub struct Application {
 }
 
 /// Information about the build.
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct Build {
     pub version: Version,
     pub build_type: Option<BuildType>,
@@ -275,7 +309,17 @@ pub struct Build {
     pub builder: Option<String>,
 }
 
-#[derive(Default, Debug, PartialEq)]
+impl Build {
+    /// The build `time` parsed as an RFC 3339 timestamp, `None` if it is absent or free-form.
+    pub fn parsed_time(&self) -> Option<DateTime<Utc>> {
+        let time = self.time.as_ref()?;
+        DateTime::parse_from_rfc3339(time.trim())
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct Version {
     pub version_major: u16,
     pub version_minor: u16,
@@ -286,7 +330,7 @@ pub struct Version {
 /// Identifies the originating GPS device that tracked a run or
 /// used to identify the type of device capable of handling
 /// the data for loading.
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct Device {
     pub name: String,
     pub unit_id: u32,
@@ -294,21 +338,24 @@ pub struct Device {
     pub version: Version,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct TrainingCenterDatabase {
     pub folders: Option<Folders>,
     pub activity_list: Option<ActivityList>,
     pub workout_list: Option<WorkoutList>,
     pub course_list: Option<CourseList>,
     pub author: Option<SourceType>,
+    /// Text of the `<!-- -->` comments with their byte position in the document, only read with
+    /// [`ReadOptions::record_comments`](crate::ReadOptions::record_comments).
+    pub comments: Vec<(usize, String)>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct CourseList {
     pub cources: Option<Vec<Course>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Course {
     pub name: Option<String>,
     pub laps: Option<Vec<CourseLap>>,
@@ -318,7 +365,7 @@ pub struct Course {
     pub creator: Option<SourceType>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct CoursePoint {
     pub name: Option<String>,
     pub time: Option<DateTime<Utc>>,
@@ -328,7 +375,7 @@ pub struct CoursePoint {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Validate)]
+#[derive(Debug, Clone, PartialEq, Default, Validate)]
 pub struct CourseLap {
     pub total_time_seconds: Option<f64>,
     pub distance_meters: Option<f64>,
@@ -343,12 +390,12 @@ pub struct CourseLap {
     pub cadence: Option<u8>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct WorkoutList {
     pub workouts: Option<Vec<Workout>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Workout {
     pub name: Option<String>,
     pub steps: Option<Vec<StepType>>,
@@ -358,14 +405,14 @@ pub struct Workout {
     pub sport: Option<Sport>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Repeat {
     pub step_id: Option<u8>,
     pub repetitions: Option<u8>,
     pub children: Option<Vec<StepType>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Step {
     pub step_id: Option<u8>,
     pub name: Option<String>,
@@ -374,57 +421,64 @@ pub struct Step {
     pub target: Option<Target>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Cadence {
     pub low: Option<f64>,
     pub high: Option<f64>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct CustomHeartRateZone {
     pub low: Option<u8>,
     pub high: Option<u8>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct CustomSpeedZone {
     pub view_as: Option<SpeedType>,
     pub low_in_meters_per_second: Option<f64>,
     pub high_in_meters_per_second: Option<f64>,
 }
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct ActivityList {
     pub activities: Vec<Activity>,
     pub multi_sport_sessions: Vec<MultiSportSession>,
 }
 
-#[derive(Debug, PartialEq)]
+impl ActivityList {
+    /// Activity with the `id`, compared as an instant so the time zone of `id` doesn't matter.
+    pub fn find_by_id<Tz: TimeZone>(&self, id: &DateTime<Tz>) -> Option<&Activity> {
+        self.activities.iter().find(|a| &a.id == id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct MultiSportSession {
     pub id: Option<DateTime<Utc>>,
     pub sports: Option<Vec<MultiActivity>>,
     pub notes: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct MultiActivity {
     pub transition: Option<ActivityLap>,
     pub activity: Option<Activity>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Folders {
     pub history: Option<History>,
     pub workouts: Option<Workouts>,
     pub courses: Option<Courses>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Courses {
     pub course_folder: Option<CourseFolder>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct CourseFolder {
     pub folders: Option<Vec<CourseFolder>>,
     pub course_name_refs: Option<Vec<String>>,
@@ -432,21 +486,21 @@ pub struct CourseFolder {
     pub name: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Workouts {
     pub running: Option<WorkoutFolder>,
     pub biking: Option<WorkoutFolder>,
     pub other: Option<WorkoutFolder>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct WorkoutFolder {
     pub folders: Option<Vec<WorkoutFolder>>,
     pub workout_name_refs: Option<Vec<String>>,
     pub name: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct History {
     pub running: Option<HistoryFolder>,
     pub biking: Option<HistoryFolder>,
@@ -454,7 +508,7 @@ pub struct History {
     pub multi_sport: Option<MultiSportFolder>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct MultiSportFolder {
     pub folders: Option<Vec<MultiSportFolder>>,
     pub multisport_activity_refs: Option<Vec<DateTime<Utc>>>,
@@ -463,7 +517,7 @@ pub struct MultiSportFolder {
     pub name: Option<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct HistoryFolder {
     pub folders: Option<Vec<HistoryFolder>>,
     pub activity_refs: Option<Vec<DateTime<Utc>>>,
@@ -473,13 +527,13 @@ pub struct HistoryFolder {
 }
 
 /// The week is written out only if the notes are present.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Week {
     pub notes: Option<String>,
     pub start_day: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Activity {
     pub id: DateTime<Utc>,
     pub laps: Vec<ActivityLap>,
@@ -487,6 +541,28 @@ pub struct Activity {
     pub training: Option<Training>,
     pub creator: Option<SourceType>,
     pub sport: Sport,
+    /// Raw XML content of the activity `<Extensions>`, kept as is since its schema is unknown.
+    pub extensions: Option<String>,
+}
+
+impl Activity {
+    /// Whether the activity is of the `sport`.
+    pub fn sport_is(&self, sport: &Sport) -> bool {
+        &self.sport == sport
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.sport_is(&Sport::Running)
+    }
+
+    pub fn is_biking(&self) -> bool {
+        self.sport_is(&Sport::Biking)
+    }
+
+    /// Laps with the `Active` intensity, i.e. without the rest intervals.
+    pub fn active_laps(&self) -> Vec<&ActivityLap> {
+        self.laps.iter().filter(|l| l.is_active()).collect()
+    }
 }
 
 impl Default for Activity {
@@ -498,33 +574,43 @@ impl Default for Activity {
             training: None,
             creator: None,
             sport: Sport::Running,
+            extensions: None,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Training {
     pub quick_workout_results: Option<QuickWorkout>,
     pub plan: Option<Plan>,
     pub virtual_partner: bool,
 }
 
-#[derive(Debug, PartialEq, Default, Validate)]
+#[derive(Debug, Clone, PartialEq, Default, Validate)]
 pub struct Plan {
     /// Non empty string up to 15 bytes
-    #[validate(length(min = 1, max = 15))]
+    #[validate(custom = "validate_plan_name")]
     pub name: Option<String>,
     pub training_type: TrainingType,
     pub interval_workout: bool,
 }
 
-#[derive(Debug, PartialEq, Default)]
+/// Checks the length of the name in bytes, unlike the `length` validator counting chars,
+/// so multibyte names are limited to less than 15 chars.
+fn validate_plan_name(name: &str) -> Result<(), ValidationError> {
+    if name.is_empty() || name.len() > 15 {
+        return Err(ValidationError::new("length"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct QuickWorkout {
     pub total_time_seconds: f64,
     pub distance_meters: f64,
 }
 
-#[derive(Debug, PartialEq, Validate)]
+#[derive(Debug, Clone, PartialEq, Validate)]
 pub struct ActivityLap {
     pub total_time_seconds: f64,
     pub distance_meters: f64,
@@ -534,6 +620,13 @@ pub struct ActivityLap {
     pub average_heart_rate_bpm: Option<u8>,
     #[validate(range(min = 1))]
     pub maximum_heart_rate_bpm: Option<u8>,
+    /// Average heart rate in percent of the maximum, when given as `HeartRateAsPercentOfMax_t`.
+    #[validate(range(max = 100))]
+    pub average_heart_rate_percent: Option<u8>,
+    /// Maximum heart rate in percent of the maximum, when given as `HeartRateAsPercentOfMax_t`.
+    #[validate(range(max = 100))]
+    pub maximum_heart_rate_percent: Option<u8>,
+    /// `Active` if the lap has no `Intensity`, which is the schema default.
     pub intensity: Intensity,
     #[validate(range(max = 254))]
     pub cadence: Option<u8>,
@@ -544,6 +637,24 @@ pub struct ActivityLap {
     pub extension: Option<ActivityLapExtension>,
 }
 
+impl ActivityLap {
+    pub fn is_active(&self) -> bool {
+        self.intensity == Intensity::Active
+    }
+
+    /// Track points with indices in `start..end`, both bounds are clamped to the number of
+    /// track points, so out of range indices yield a shorter or an empty slice.
+    pub fn track_points_between(&self, start: usize, end: usize) -> &[TrackPoint] {
+        let end = end.min(self.track_points.len());
+        &self.track_points[start.min(end)..end]
+    }
+
+    /// Copy of the lap extension, or an extension without any values if the lap has none.
+    pub fn extension_or_default(&self) -> ActivityLapExtension {
+        self.extension.clone().unwrap_or_default()
+    }
+}
+
 impl Default for ActivityLap {
     fn default() -> Self {
         Self {
@@ -553,6 +664,8 @@ impl Default for ActivityLap {
             calories: 0,
             average_heart_rate_bpm: None,
             maximum_heart_rate_bpm: None,
+            average_heart_rate_percent: None,
+            maximum_heart_rate_percent: None,
             intensity: Intensity::Active,
             cadence: None,
             trigger_method: TriggerMethod::Manual,
@@ -564,9 +677,10 @@ impl Default for ActivityLap {
     }
 }
 
-#[derive(Debug, PartialEq, Validate)]
+#[derive(Debug, Clone, PartialEq, Default, Validate)]
 pub struct TrackPoint {
-    pub time: DateTime<Utc>,
+    /// Required for activities, but course track points may omit it.
+    pub time: Option<DateTime<Utc>>,
     pub position: Option<Position>,
     pub altitude_meters: Option<f64>,
     pub distance_meters: Option<f64>,
@@ -577,22 +691,35 @@ pub struct TrackPoint {
     pub extension: Option<ActivityTrackPointExtension>,
 }
 
-impl Default for TrackPoint {
-    fn default() -> Self {
-        Self {
-            time: Utc::now(),
-            position: None,
-            altitude_meters: None,
-            distance_meters: None,
-            heart_rate_bpm: None,
-            cadence: None,
-            sensor_state: None,
-            extension: None,
+impl TrackPoint {
+    /// Cadence of the track point, falling back to the `RunCadence` extension.
+    pub fn effective_cadence(&self) -> Option<u8> {
+        self.cadence
+            .or_else(|| self.extension.as_ref().and_then(|e| e.run_cadence))
+    }
+
+    /// Running cadence in steps per minute of both feet for a `Running` activity, `None`
+    /// for other sports. Devices report the steps of a single foot, so the `RunCadence`
+    /// extension, or the `Cadence` if it's absent, is doubled.
+    pub fn normalized_running_cadence(&self, sport: &Sport) -> Option<u16> {
+        if sport != &Sport::Running {
+            return None;
         }
+        let cadence = self
+            .extension
+            .as_ref()
+            .and_then(|e| e.run_cadence)
+            .or(self.cadence)?;
+        Some(u16::from(cadence) * 2)
+    }
+
+    /// Speed in meters per second recorded in the `Speed` extension.
+    pub fn speed(&self) -> Option<f64> {
+        self.extension.as_ref().and_then(|e| e.speed)
     }
 }
 
-#[derive(Debug, PartialEq, Default, Validate)]
+#[derive(Debug, Clone, PartialEq, Default, Validate)]
 pub struct Position {
     #[validate(range(min = - 90.0, max = 90.0))]
     pub latitude_degrees: f64,
@@ -602,7 +729,7 @@ pub struct Position {
 
 // Activity Extensions
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CadenceSensorType {
     Footpod,
     Bike,
@@ -615,14 +742,12 @@ impl FromStr for CadenceSensorType {
         match s {
             "Footpod" => Ok(Self::Footpod),
             "Bike" => Ok(Self::Bike),
-            _ => {
-                return Err(UnknownEnumValueError::CadenceSensorType(s.to_string()));
-            }
+            _ => Err(UnknownEnumValueError::CadenceSensorType(s.to_string())),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Default, Validate)]
+#[derive(Debug, Clone, PartialEq, Default, Validate)]
 pub struct ActivityTrackPointExtension {
     pub speed: Option<f64>,
     #[validate(range(max = 254))]
@@ -631,7 +756,7 @@ pub struct ActivityTrackPointExtension {
     pub cadence_sensor: Option<CadenceSensorType>,
 }
 
-#[derive(Debug, PartialEq, Default, Validate)]
+#[derive(Debug, Clone, PartialEq, Default, Validate)]
 pub struct ActivityLapExtension {
     pub avg_speed: Option<f64>,
     #[validate(range(max = 254))]
@@ -643,4 +768,151 @@ pub struct ActivityLapExtension {
     pub steps: Option<u16>,
     pub avg_watts: Option<u16>,
     pub max_watts: Option<u16>,
+    /// Maximum speed in meters per second. Not defined by the schema, but some devices
+    /// report it in the extension as `MaximumSpeed` instead of the lap itself.
+    pub max_speed: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    #[test]
+    fn source_version_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let tc_db = crate::read(tcx_bytes).unwrap();
+        let creator = tc_db.activity_list.unwrap().activities[0]
+            .creator
+            .clone()
+            .unwrap();
+        let version = creator.version();
+        assert_eq!((5, 1), (version.version_major, version.version_minor));
+        assert_eq!(0, tc_db.author.unwrap().version().version_major);
+    }
+
+    #[test]
+    fn build_parsed_time_test() {
+        let mut build = Build {
+            time: Some("2021-01-15T10:20:30+02:00".to_string()),
+            ..Build::default()
+        };
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2021, 1, 15, 8, 20, 30).unwrap()),
+            build.parsed_time()
+        );
+        build.time = Some("Jan 15 2021, 10:20:30".to_string());
+        assert_eq!(None, build.parsed_time());
+        assert_eq!(Some("Jan 15 2021, 10:20:30"), build.time.as_deref());
+        build.time = None;
+        assert_eq!(None, build.parsed_time());
+    }
+
+    #[test]
+    fn activity_sport_predicates_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let activity = &list.activities[0];
+        assert!(activity.is_running());
+        assert!(!activity.is_biking());
+        assert!(activity.sport_is(&Sport::Running));
+        assert!(!activity.sport_is(&Sport::Other));
+    }
+
+    #[test]
+    fn activity_list_find_by_id_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let id = list.activities[0].id;
+        assert_eq!(Some(&list.activities[0]), list.find_by_id(&id));
+        let shifted = id.with_timezone(&FixedOffset::east_opt(3 * 3600).unwrap());
+        assert_eq!(Some(&list.activities[0]), list.find_by_id(&shifted));
+        assert_eq!(None, list.find_by_id(&(id + chrono::Duration::seconds(1))));
+    }
+
+    #[test]
+    fn lap_track_points_between_test() {
+        let lap = ActivityLap {
+            track_points: (0..5)
+                .map(|i| TrackPoint {
+                    distance_meters: Some(i as f64),
+                    ..TrackPoint::default()
+                })
+                .collect(),
+            ..ActivityLap::default()
+        };
+        assert_eq!(&lap.track_points[1..3], lap.track_points_between(1, 3));
+        assert_eq!(&lap.track_points[2..], lap.track_points_between(2, 100));
+        assert!(lap.track_points_between(7, 100).is_empty());
+        assert!(lap.track_points_between(3, 1).is_empty());
+    }
+
+    #[test]
+    fn lap_extension_or_default_test() {
+        let mut lap = ActivityLap::default();
+        assert_eq!(ActivityLapExtension::default(), lap.extension_or_default());
+        assert_eq!(None, lap.extension_or_default().avg_speed);
+        lap.extension = Some(ActivityLapExtension {
+            avg_speed: Some(3.2),
+            ..ActivityLapExtension::default()
+        });
+        assert_eq!(Some(3.2), lap.extension_or_default().avg_speed);
+    }
+
+    #[test]
+    fn normalized_running_cadence_test() {
+        let mut tp = TrackPoint {
+            cadence: Some(90),
+            ..TrackPoint::default()
+        };
+        assert_eq!(Some(180), tp.normalized_running_cadence(&Sport::Running));
+        assert_eq!(None, tp.normalized_running_cadence(&Sport::Biking));
+        tp.extension = Some(ActivityTrackPointExtension {
+            run_cadence: Some(85),
+            ..ActivityTrackPointExtension::default()
+        });
+        assert_eq!(Some(170), tp.normalized_running_cadence(&Sport::Running));
+        assert_eq!(
+            None,
+            TrackPoint::default().normalized_running_cadence(&Sport::Running)
+        );
+    }
+
+    #[test]
+    fn track_point_speed_test() {
+        let tp = TrackPoint {
+            extension: Some(ActivityTrackPointExtension {
+                speed: Some(3.4),
+                ..ActivityTrackPointExtension::default()
+            }),
+            ..TrackPoint::default()
+        };
+        assert_eq!(Some(3.4), tp.speed());
+        assert_eq!(None, TrackPoint::default().speed());
+    }
+
+    #[test]
+    fn trigger_method_from_str_test() {
+        assert_eq!(
+            TriggerMethod::HeartRate,
+            TriggerMethod::from_str("HeartRate").unwrap()
+        );
+        assert_eq!(
+            TriggerMethod::HeartRate,
+            TriggerMethod::from_str("Heart Rate").unwrap()
+        );
+        assert!(TriggerMethod::from_str("Heart  Rate").is_err());
+    }
+
+    #[test]
+    fn active_laps_test() {
+        let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+        let list = crate::read(tcx_bytes).unwrap().activity_list.unwrap();
+        let mut activity = list.activities[0].clone();
+        assert_eq!(10, activity.active_laps().len());
+        activity.laps[1].intensity = Intensity::Resting;
+        assert!(!activity.laps[1].is_active());
+        assert_eq!(9, activity.active_laps().len());
+    }
 }
@@ -0,0 +1,1037 @@
+use std::fmt::Display;
+use std::io::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::{Result, Writer};
+use thiserror::Error;
+
+use crate::types::*;
+
+const TCD_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2";
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+const ACTIVITY_EXTENSION_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/ActivityExtension/v2";
+
+#[derive(Error, Debug)]
+pub enum WriteError {
+    #[error("error writing XML '{0}'")]
+    XmlWriteError(#[from] quick_xml::Error),
+    #[error("written XML is not valid UTF-8 '{0}'")]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+}
+
+impl TrainingCenterDatabase {
+    /// Writes the database as TCX xml into a string.
+    /// ```
+    /// let tcx_bytes: &[u8] = include_bytes!("../test_resources/test.tcx.xml");
+    /// let tc_db = quick_tcx::read(tcx_bytes).unwrap();
+    /// let tcx = tc_db.to_tcx_string().unwrap();
+    /// assert_eq!(tc_db, quick_tcx::read_str(&tcx).unwrap());
+    /// ```
+    pub fn to_tcx_string(&self) -> std::result::Result<String, WriteError> {
+        let mut bytes = Vec::new();
+        crate::write(self, &mut bytes)?;
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+pub fn write_training_center<W: Write>(
+    writer: &mut Writer<W>,
+    tc_db: &TrainingCenterDatabase,
+) -> Result<()> {
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("TrainingCenterDatabase")
+        .with_attribute(("xmlns", TCD_NAMESPACE))
+        .with_attribute(("xmlns:xsi", XSI_NAMESPACE))
+        .write_inner_content(|w| {
+            if let Some(folders) = &tc_db.folders {
+                write_folders(w, folders)?;
+            }
+            if let Some(al) = &tc_db.activity_list {
+                w.create_element("Activities").write_inner_content(|w| {
+                    for activity in &al.activities {
+                        write_activity(w, activity)?;
+                    }
+                    for session in &al.multi_sport_sessions {
+                        write_multi_sport_session(w, session)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            if let Some(wl) = &tc_db.workout_list {
+                w.create_element("Workouts").write_inner_content(|w| {
+                    for workout in wl.workouts.iter().flatten() {
+                        write_workout(w, workout)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            if let Some(cl) = &tc_db.course_list {
+                w.create_element("Courses").write_inner_content(|w| {
+                    for course in cl.cources.iter().flatten() {
+                        write_course(w, course)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            if let Some(author) = &tc_db.author {
+                write_source(w, "Author", author)?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+pub(crate) fn write_text<W: Write>(writer: &mut Writer<W>, name: &str, text: &str) -> Result<()> {
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(text))?;
+    Ok(())
+}
+
+pub(crate) fn write_value<W: Write, T: Display>(
+    writer: &mut Writer<W>,
+    name: &str,
+    value: T,
+) -> Result<()> {
+    write_text(writer, name, &value.to_string())
+}
+
+pub(crate) fn write_opt_value<W: Write, T: Display>(
+    writer: &mut Writer<W>,
+    name: &str,
+    value: &Option<T>,
+) -> Result<()> {
+    match value {
+        Some(v) => write_value(writer, name, v),
+        None => Ok(()),
+    }
+}
+
+fn write_opt_text<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    text: &Option<String>,
+) -> Result<()> {
+    match text {
+        Some(t) => write_text(writer, name, t),
+        None => Ok(()),
+    }
+}
+
+/// Writes a heart rate element of `HeartRateInBeatsPerMinute_t` or `HeartRateAsPercentOfMax_t` type.
+fn write_heart_rate<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    value: u8,
+    hr_type: Option<&str>,
+) -> Result<()> {
+    let mut element = writer.create_element(name);
+    if let Some(t) = hr_type {
+        element = element.with_attribute(("xsi:type", t));
+    }
+    element.write_inner_content(|w| write_value(w, "Value", value))?;
+    Ok(())
+}
+
+pub(crate) fn format_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn write_folders<W: Write>(writer: &mut Writer<W>, folders: &Folders) -> Result<()> {
+    writer.create_element("Folders").write_inner_content(|w| {
+        if let Some(history) = &folders.history {
+            w.create_element("History").write_inner_content(|w| {
+                let sports = [
+                    ("Running", &history.running),
+                    ("Biking", &history.biking),
+                    ("Other", &history.other),
+                ];
+                for (name, folder) in sports {
+                    if let Some(f) = folder {
+                        write_history_folder(w, name, f)?;
+                    }
+                }
+                if let Some(f) = &history.multi_sport {
+                    write_multi_sport_folder(w, "MultiSport", f)?;
+                }
+                Ok(())
+            })?;
+        }
+        if let Some(workouts) = &folders.workouts {
+            w.create_element("Workouts").write_inner_content(|w| {
+                let sports = [
+                    ("Running", &workouts.running),
+                    ("Biking", &workouts.biking),
+                    ("Other", &workouts.other),
+                ];
+                for (name, folder) in sports {
+                    if let Some(f) = folder {
+                        write_workout_folder(w, name, f)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        if let Some(courses) = &folders.courses {
+            w.create_element("Courses").write_inner_content(|w| {
+                if let Some(f) = &courses.course_folder {
+                    write_course_folder(w, "CourseFolder", f)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn write_history_folder<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    folder: &HistoryFolder,
+) -> Result<()> {
+    let mut element = writer.create_element(name);
+    if let Some(n) = &folder.name {
+        element = element.with_attribute(("Name", n.as_str()));
+    }
+    element.write_inner_content(|w| {
+        for f in folder.folders.iter().flatten() {
+            write_history_folder(w, "Folder", f)?;
+        }
+        for id in folder.activity_refs.iter().flatten() {
+            write_activity_ref(w, "ActivityRef", id)?;
+        }
+        for week in folder.weeks.iter().flatten() {
+            write_week(w, week)?;
+        }
+        write_opt_text(w, "Notes", &folder.notes)
+    })?;
+    Ok(())
+}
+
+fn write_multi_sport_folder<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    folder: &MultiSportFolder,
+) -> Result<()> {
+    let mut element = writer.create_element(name);
+    if let Some(n) = &folder.name {
+        element = element.with_attribute(("Name", n.as_str()));
+    }
+    element.write_inner_content(|w| {
+        for f in folder.folders.iter().flatten() {
+            write_multi_sport_folder(w, "Folder", f)?;
+        }
+        for id in folder.multisport_activity_refs.iter().flatten() {
+            write_activity_ref(w, "MultiSportActivityRef", id)?;
+        }
+        for week in folder.weeks.iter().flatten() {
+            write_week(w, week)?;
+        }
+        write_opt_text(w, "Notes", &folder.notes)
+    })?;
+    Ok(())
+}
+
+fn write_activity_ref<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    id: &DateTime<Utc>,
+) -> Result<()> {
+    writer
+        .create_element(name)
+        .write_inner_content(|w| write_text(w, "Id", &format_time(id)))?;
+    Ok(())
+}
+
+fn write_name_ref<W: Write>(writer: &mut Writer<W>, name: &str, id: &str) -> Result<()> {
+    writer
+        .create_element(name)
+        .write_inner_content(|w| write_text(w, "Id", id))?;
+    Ok(())
+}
+
+fn write_week<W: Write>(writer: &mut Writer<W>, week: &Week) -> Result<()> {
+    let mut element = writer.create_element("Week");
+    let start_day = week.start_day.map(|d| d.format("%Y-%m-%d").to_string());
+    if let Some(d) = &start_day {
+        element = element.with_attribute(("StartDay", d.as_str()));
+    }
+    element.write_inner_content(|w| write_opt_text(w, "Notes", &week.notes))?;
+    Ok(())
+}
+
+fn write_workout_folder<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    folder: &WorkoutFolder,
+) -> Result<()> {
+    let mut element = writer.create_element(name);
+    if let Some(n) = &folder.name {
+        element = element.with_attribute(("Name", n.as_str()));
+    }
+    element.write_inner_content(|w| {
+        for f in folder.folders.iter().flatten() {
+            write_workout_folder(w, "Folder", f)?;
+        }
+        for id in folder.workout_name_refs.iter().flatten() {
+            write_name_ref(w, "WorkoutNameRef", id)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn write_course_folder<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    folder: &CourseFolder,
+) -> Result<()> {
+    let mut element = writer.create_element(name);
+    if let Some(n) = &folder.name {
+        element = element.with_attribute(("Name", n.as_str()));
+    }
+    element.write_inner_content(|w| {
+        for f in folder.folders.iter().flatten() {
+            write_course_folder(w, "Folder", f)?;
+        }
+        for id in folder.course_name_refs.iter().flatten() {
+            write_name_ref(w, "CourseNameRef", id)?;
+        }
+        write_opt_text(w, "Notes", &folder.notes)
+    })?;
+    Ok(())
+}
+
+fn write_multi_sport_session<W: Write>(
+    writer: &mut Writer<W>,
+    session: &MultiSportSession,
+) -> Result<()> {
+    writer
+        .create_element("MultiSportSession")
+        .write_inner_content(|w| {
+            if let Some(id) = &session.id {
+                write_text(w, "Id", &format_time(id))?;
+            }
+            for (i, sport) in session.sports.iter().flatten().enumerate() {
+                let name = if i == 0 { "FirstSport" } else { "NextSport" };
+                w.create_element(name).write_inner_content(|w| {
+                    if let Some(transition) = &sport.transition {
+                        write_activity_lap(w, "Transition", transition)?;
+                    }
+                    if let Some(activity) = &sport.activity {
+                        write_activity(w, activity)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            write_opt_text(w, "Notes", &session.notes)
+        })?;
+    Ok(())
+}
+
+fn write_activity<W: Write>(writer: &mut Writer<W>, activity: &Activity) -> Result<()> {
+    writer
+        .create_element("Activity")
+        .with_attribute(("Sport", sport_name(&activity.sport)))
+        .write_inner_content(|w| {
+            write_text(w, "Id", &format_time(&activity.id))?;
+            for lap in &activity.laps {
+                write_activity_lap(w, "Lap", lap)?;
+            }
+            write_opt_text(w, "Notes", &activity.notes)?;
+            if let Some(training) = &activity.training {
+                write_training(w, training)?;
+            }
+            if let Some(creator) = &activity.creator {
+                write_source(w, "Creator", creator)?;
+            }
+            if let Some(extensions) = &activity.extensions {
+                // the content is kept as raw XML, so it's written without escaping
+                w.create_element("Extensions").write_inner_content(|w| {
+                    w.write_event(Event::Text(BytesText::from_escaped(extensions.as_str())))
+                })?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_activity_lap<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    lap: &ActivityLap,
+) -> Result<()> {
+    writer
+        .create_element(name)
+        .with_attribute(("StartTime", format_time(&lap.start_time).as_str()))
+        .write_inner_content(|w| {
+            write_value(w, "TotalTimeSeconds", lap.total_time_seconds)?;
+            write_value(w, "DistanceMeters", lap.distance_meters)?;
+            write_opt_value(w, "MaximumSpeed", &lap.maximum_speed)?;
+            write_value(w, "Calories", lap.calories)?;
+            match (lap.average_heart_rate_bpm, lap.average_heart_rate_percent) {
+                (Some(bpm), _) => write_heart_rate(w, "AverageHeartRateBpm", bpm, None)?,
+                (None, Some(percent)) => write_heart_rate(
+                    w,
+                    "AverageHeartRateBpm",
+                    percent,
+                    Some("HeartRateAsPercentOfMax_t"),
+                )?,
+                (None, None) => (),
+            }
+            match (lap.maximum_heart_rate_bpm, lap.maximum_heart_rate_percent) {
+                (Some(bpm), _) => write_heart_rate(w, "MaximumHeartRateBpm", bpm, None)?,
+                (None, Some(percent)) => write_heart_rate(
+                    w,
+                    "MaximumHeartRateBpm",
+                    percent,
+                    Some("HeartRateAsPercentOfMax_t"),
+                )?,
+                (None, None) => (),
+            }
+            write_text(w, "Intensity", intensity_name(&lap.intensity))?;
+            write_opt_value(w, "Cadence", &lap.cadence)?;
+            write_text(w, "TriggerMethod", trigger_method_name(&lap.trigger_method))?;
+            if !lap.track_points.is_empty() {
+                write_track(w, &lap.track_points)?;
+            }
+            write_opt_text(w, "Notes", &lap.notes)?;
+            if let Some(ext) = &lap.extension {
+                write_activity_lap_extension(w, ext)?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_track<W: Write>(writer: &mut Writer<W>, track_points: &[TrackPoint]) -> Result<()> {
+    writer.create_element("Track").write_inner_content(|w| {
+        for tp in track_points {
+            write_track_point(w, tp)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn write_track_point<W: Write>(writer: &mut Writer<W>, tp: &TrackPoint) -> Result<()> {
+    writer
+        .create_element("Trackpoint")
+        .write_inner_content(|w| {
+            if let Some(time) = &tp.time {
+                write_text(w, "Time", &format_time(time))?;
+            }
+            if let Some(pos) = &tp.position {
+                write_position(w, "Position", pos)?;
+            }
+            write_opt_value(w, "AltitudeMeters", &tp.altitude_meters)?;
+            write_opt_value(w, "DistanceMeters", &tp.distance_meters)?;
+            if let Some(hr) = tp.heart_rate_bpm {
+                write_heart_rate(w, "HeartRateBpm", hr, None)?;
+            }
+            write_opt_value(w, "Cadence", &tp.cadence)?;
+            if let Some(state) = &tp.sensor_state {
+                write_text(w, "SensorState", sensor_state_name(state))?;
+            }
+            if let Some(ext) = &tp.extension {
+                write_activity_track_point_extension(w, ext)?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_position<W: Write>(writer: &mut Writer<W>, name: &str, pos: &Position) -> Result<()> {
+    writer.create_element(name).write_inner_content(|w| {
+        write_value(w, "LatitudeDegrees", pos.latitude_degrees)?;
+        write_value(w, "LongitudeDegrees", pos.longitude_degrees)
+    })?;
+    Ok(())
+}
+
+fn write_activity_track_point_extension<W: Write>(
+    writer: &mut Writer<W>,
+    ext: &ActivityTrackPointExtension,
+) -> Result<()> {
+    writer
+        .create_element("Extensions")
+        .write_inner_content(|w| {
+            let mut element = w
+                .create_element("TPX")
+                .with_attribute(("xmlns", ACTIVITY_EXTENSION_NAMESPACE));
+            if let Some(sensor) = &ext.cadence_sensor {
+                element = element.with_attribute(("CadenceSensor", cadence_sensor_name(sensor)));
+            }
+            element.write_inner_content(|w| {
+                write_opt_value(w, "Speed", &ext.speed)?;
+                write_opt_value(w, "RunCadence", &ext.run_cadence)?;
+                write_opt_value(w, "Watts", &ext.watts)
+            })?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_activity_lap_extension<W: Write>(
+    writer: &mut Writer<W>,
+    ext: &ActivityLapExtension,
+) -> Result<()> {
+    writer
+        .create_element("Extensions")
+        .write_inner_content(|w| {
+            w.create_element("LX")
+                .with_attribute(("xmlns", ACTIVITY_EXTENSION_NAMESPACE))
+                .write_inner_content(|w| {
+                    write_opt_value(w, "AvgSpeed", &ext.avg_speed)?;
+                    write_opt_value(w, "MaxBikeCadence", &ext.max_bike_cadence)?;
+                    write_opt_value(w, "AvgRunCadence", &ext.avg_run_cadence)?;
+                    write_opt_value(w, "MaxRunCadence", &ext.max_run_cadence)?;
+                    write_opt_value(w, "Steps", &ext.steps)?;
+                    write_opt_value(w, "AvgWatts", &ext.avg_watts)?;
+                    write_opt_value(w, "MaxWatts", &ext.max_watts)?;
+                    write_opt_value(w, "MaximumSpeed", &ext.max_speed)
+                })?;
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_training<W: Write>(writer: &mut Writer<W>, training: &Training) -> Result<()> {
+    writer
+        .create_element("Training")
+        .with_attribute((
+            "VirtualPartner",
+            training.virtual_partner.to_string().as_str(),
+        ))
+        .write_inner_content(|w| {
+            if let Some(qw) = &training.quick_workout_results {
+                w.create_element("QuickWorkoutResults")
+                    .write_inner_content(|w| {
+                        write_value(w, "TotalTimeSeconds", qw.total_time_seconds)?;
+                        write_value(w, "DistanceMeters", qw.distance_meters)
+                    })?;
+            }
+            if let Some(plan) = &training.plan {
+                w.create_element("Plan")
+                    .with_attribute(("Type", training_type_name(&plan.training_type)))
+                    .with_attribute((
+                        "IntervalWorkout",
+                        plan.interval_workout.to_string().as_str(),
+                    ))
+                    .write_inner_content(|w| write_opt_text(w, "Name", &plan.name))?;
+            }
+            Ok(())
+        })?;
+    Ok(())
+}
+
+fn write_source<W: Write>(writer: &mut Writer<W>, name: &str, source: &SourceType) -> Result<()> {
+    match source {
+        SourceType::Application(a) => writer
+            .create_element(name)
+            .with_attribute(("xsi:type", "Application_t"))
+            .write_inner_content(|w| {
+                write_text(w, "Name", &a.name)?;
+                write_build(w, &a.build)?;
+                write_text(w, "LangID", &a.lang_id)?;
+                write_text(w, "PartNumber", &a.part_number)
+            })?,
+        SourceType::Device(d) => writer
+            .create_element(name)
+            .with_attribute(("xsi:type", "Device_t"))
+            .write_inner_content(|w| write_device_content(w, d))?,
+    };
+    Ok(())
+}
+
+fn write_device_content<W: Write>(writer: &mut Writer<W>, device: &Device) -> Result<()> {
+    write_text(writer, "Name", &device.name)?;
+    write_value(writer, "UnitId", device.unit_id)?;
+    write_value(writer, "ProductID", device.product_id)?;
+    write_version(writer, &device.version)
+}
+
+fn write_build<W: Write>(writer: &mut Writer<W>, build: &Build) -> Result<()> {
+    writer.create_element("Build").write_inner_content(|w| {
+        write_version(w, &build.version)?;
+        if let Some(t) = &build.build_type {
+            write_text(w, "Type", build_type_name(t))?;
+        }
+        write_opt_text(w, "Time", &build.time)?;
+        write_opt_text(w, "Builder", &build.builder)
+    })?;
+    Ok(())
+}
+
+fn write_version<W: Write>(writer: &mut Writer<W>, version: &Version) -> Result<()> {
+    writer.create_element("Version").write_inner_content(|w| {
+        write_value(w, "VersionMajor", version.version_major)?;
+        write_value(w, "VersionMinor", version.version_minor)?;
+        write_opt_value(w, "BuildMajor", &version.build_major)?;
+        write_opt_value(w, "BuildMinor", &version.build_minor)
+    })?;
+    Ok(())
+}
+
+fn write_course<W: Write>(writer: &mut Writer<W>, course: &Course) -> Result<()> {
+    writer.create_element("Course").write_inner_content(|w| {
+        write_opt_text(w, "Name", &course.name)?;
+        for lap in course.laps.iter().flatten() {
+            write_course_lap(w, lap)?;
+        }
+        if let Some(tps) = &course.track_points {
+            write_track(w, tps)?;
+        }
+        write_opt_text(w, "Notes", &course.notes)?;
+        if let Some(point) = &course.course_point {
+            write_course_point(w, point)?;
+        }
+        if let Some(creator) = &course.creator {
+            write_source(w, "Creator", creator)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn write_course_lap<W: Write>(writer: &mut Writer<W>, lap: &CourseLap) -> Result<()> {
+    writer.create_element("Lap").write_inner_content(|w| {
+        write_opt_value(w, "TotalTimeSeconds", &lap.total_time_seconds)?;
+        write_opt_value(w, "DistanceMeters", &lap.distance_meters)?;
+        if let Some(pos) = &lap.begin_position {
+            write_position(w, "BeginPosition", pos)?;
+        }
+        write_opt_value(w, "BeginAltitudeMeters", &lap.begin_altitude_meters)?;
+        if let Some(pos) = &lap.end_position {
+            write_position(w, "EndPosition", pos)?;
+        }
+        write_opt_value(w, "EndAltitudeMeters", &lap.end_altitude_meters)?;
+        if let Some(hr) = lap.average_heart_rate_bpm {
+            write_heart_rate(w, "AverageHeartRateBpm", hr, None)?;
+        }
+        if let Some(hr) = lap.maximum_heart_rate_bpm {
+            write_heart_rate(w, "MaximumHeartRateBpm", hr, None)?;
+        }
+        if let Some(intensity) = &lap.intensity {
+            write_text(w, "Intensity", intensity_name(intensity))?;
+        }
+        write_opt_value(w, "Cadence", &lap.cadence)
+    })?;
+    Ok(())
+}
+
+fn write_course_point<W: Write>(writer: &mut Writer<W>, point: &CoursePoint) -> Result<()> {
+    writer
+        .create_element("CoursePoint")
+        .write_inner_content(|w| {
+            write_opt_text(w, "Name", &point.name)?;
+            if let Some(time) = &point.time {
+                write_text(w, "Time", &format_time(time))?;
+            }
+            if let Some(pos) = &point.position {
+                write_position(w, "Position", pos)?;
+            }
+            write_opt_value(w, "AltitudeMeters", &point.altitude_meters)?;
+            if let Some(t) = &point.point_type {
+                write_text(w, "PointType", course_point_type_name(t))?;
+            }
+            write_opt_text(w, "Notes", &point.notes)
+        })?;
+    Ok(())
+}
+
+fn write_workout<W: Write>(writer: &mut Writer<W>, workout: &Workout) -> Result<()> {
+    let mut element = writer.create_element("Workout");
+    if let Some(sport) = &workout.sport {
+        element = element.with_attribute(("Sport", sport_name(sport)));
+    }
+    element.write_inner_content(|w| {
+        write_opt_text(w, "Name", &workout.name)?;
+        for step in workout.steps.iter().flatten() {
+            write_step(w, "Step", step)?;
+        }
+        if let Some(day) = &workout.scheduled_on {
+            write_text(w, "ScheduledOn", &day.format("%Y-%m-%d").to_string())?;
+        }
+        write_opt_text(w, "Notes", &workout.notes)?;
+        if let Some(creator) = &workout.creator {
+            write_source(w, "Creator", creator)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn write_step<W: Write>(writer: &mut Writer<W>, name: &str, step: &StepType) -> Result<()> {
+    match step {
+        StepType::Step(s) => writer
+            .create_element(name)
+            .with_attribute(("xsi:type", "Step_t"))
+            .write_inner_content(|w| {
+                write_opt_value(w, "StepId", &s.step_id)?;
+                write_opt_text(w, "Name", &s.name)?;
+                write_duration(w, &s.duration)?;
+                if let Some(intensity) = &s.intensity {
+                    write_text(w, "Intensity", intensity_name(intensity))?;
+                }
+                if let Some(target) = &s.target {
+                    write_target(w, target)?;
+                }
+                Ok(())
+            })?,
+        StepType::Repeat(r) => writer
+            .create_element(name)
+            .with_attribute(("xsi:type", "Repeat_t"))
+            .write_inner_content(|w| {
+                write_opt_value(w, "StepId", &r.step_id)?;
+                write_opt_value(w, "Repetitions", &r.repetitions)?;
+                for child in r.children.iter().flatten() {
+                    write_step(w, "Child", child)?;
+                }
+                Ok(())
+            })?,
+    };
+    Ok(())
+}
+
+/// Writes the step duration, a missing one is written as `UserInitiated_t`.
+fn write_duration<W: Write>(writer: &mut Writer<W>, duration: &Option<Duration>) -> Result<()> {
+    let element = writer.create_element("Duration");
+    match duration {
+        Some(Duration::Time(s)) => element
+            .with_attribute(("xsi:type", "Time_t"))
+            .write_inner_content(|w| write_value(w, "Seconds", s))?,
+        Some(Duration::Distance(m)) => element
+            .with_attribute(("xsi:type", "Distance_t"))
+            .write_inner_content(|w| write_value(w, "Meters", m))?,
+        Some(Duration::HeartRateAbove(hr)) => element
+            .with_attribute(("xsi:type", "HeartRateAbove_t"))
+            .write_inner_content(|w| {
+                write_heart_rate(w, "HeartRate", *hr, Some("HeartRateInBeatsPerMinute_t"))
+            })?,
+        Some(Duration::HeartRateBelow(hr)) => element
+            .with_attribute(("xsi:type", "HeartRateBelow_t"))
+            .write_inner_content(|w| {
+                write_heart_rate(w, "HeartRate", *hr, Some("HeartRateInBeatsPerMinute_t"))
+            })?,
+        Some(Duration::CaloriesBurned(c)) => element
+            .with_attribute(("xsi:type", "CaloriesBurned_t"))
+            .write_inner_content(|w| write_value(w, "Calories", c))?,
+        None => element
+            .with_attribute(("xsi:type", "UserInitiated_t"))
+            .write_empty()?,
+    };
+    Ok(())
+}
+
+fn write_target<W: Write>(writer: &mut Writer<W>, target: &Target) -> Result<()> {
+    let element = writer.create_element("Target");
+    match target {
+        Target::Speed(zone) => element
+            .with_attribute(("xsi:type", "Speed_t"))
+            .write_inner_content(|w| write_zone(w, "SpeedZone", zone))?,
+        Target::HeartRate(zone) => element
+            .with_attribute(("xsi:type", "HeartRate_t"))
+            .write_inner_content(|w| write_zone(w, "HeartRateZone", zone))?,
+        Target::Cadence(cadence) => element
+            .with_attribute(("xsi:type", "Cadence_t"))
+            .write_inner_content(|w| {
+                write_opt_value(w, "Low", &cadence.low)?;
+                write_opt_value(w, "High", &cadence.high)
+            })?,
+        Target::None => element
+            .with_attribute(("xsi:type", "None_t"))
+            .write_empty()?,
+    };
+    Ok(())
+}
+
+fn write_zone<W: Write>(writer: &mut Writer<W>, name: &str, zone: &Zone) -> Result<()> {
+    let element = writer.create_element(name);
+    match zone {
+        Zone::PredefinedSpeedZone(n) => element
+            .with_attribute(("xsi:type", "PredefinedSpeedZone_t"))
+            .write_inner_content(|w| write_value(w, "Number", n))?,
+        Zone::PredefinedHeartRateZone(n) => element
+            .with_attribute(("xsi:type", "PredefinedHeartRateZone_t"))
+            .write_inner_content(|w| write_value(w, "Number", n))?,
+        Zone::CustomSpeedZone(z) => element
+            .with_attribute(("xsi:type", "CustomSpeedZone_t"))
+            .write_inner_content(|w| {
+                if let Some(view_as) = &z.view_as {
+                    write_text(w, "ViewAs", speed_type_name(view_as))?;
+                }
+                write_opt_value(w, "LowInMetersPerSecond", &z.low_in_meters_per_second)?;
+                write_opt_value(w, "HighInMetersPerSecond", &z.high_in_meters_per_second)
+            })?,
+        Zone::CustomHeartRateZone(z) => element
+            .with_attribute(("xsi:type", "CustomHeartRateZone_t"))
+            .write_inner_content(|w| {
+                if let Some(low) = z.low {
+                    write_heart_rate(w, "Low", low, Some("HeartRateInBeatsPerMinute_t"))?;
+                }
+                if let Some(high) = z.high {
+                    write_heart_rate(w, "High", high, Some("HeartRateInBeatsPerMinute_t"))?;
+                }
+                Ok(())
+            })?,
+    };
+    Ok(())
+}
+
+pub(crate) fn sport_name(sport: &Sport) -> &'static str {
+    match sport {
+        Sport::Running => "Running",
+        Sport::Biking => "Biking",
+        Sport::Other => "Other",
+    }
+}
+
+fn intensity_name(intensity: &Intensity) -> &'static str {
+    match intensity {
+        Intensity::Active => "Active",
+        Intensity::Resting => "Resting",
+    }
+}
+
+fn trigger_method_name(trigger_method: &TriggerMethod) -> &'static str {
+    match trigger_method {
+        TriggerMethod::Manual => "Manual",
+        TriggerMethod::Distance => "Distance",
+        TriggerMethod::Location => "Location",
+        TriggerMethod::Time => "Time",
+        TriggerMethod::HeartRate => "HeartRate",
+    }
+}
+
+fn sensor_state_name(sensor_state: &SensorState) -> &'static str {
+    match sensor_state {
+        SensorState::Present => "Present",
+        SensorState::Absent => "Absent",
+    }
+}
+
+fn cadence_sensor_name(sensor: &CadenceSensorType) -> &'static str {
+    match sensor {
+        CadenceSensorType::Footpod => "Footpod",
+        CadenceSensorType::Bike => "Bike",
+    }
+}
+
+fn build_type_name(build_type: &BuildType) -> &'static str {
+    match build_type {
+        BuildType::Internal => "Internal",
+        BuildType::Alpha => "Alpha",
+        BuildType::Beta => "Beta",
+        BuildType::Release => "Release",
+    }
+}
+
+fn training_type_name(training_type: &TrainingType) -> &'static str {
+    match training_type {
+        TrainingType::Workout => "Workout",
+        TrainingType::Course => "Course",
+    }
+}
+
+fn speed_type_name(speed_type: &SpeedType) -> &'static str {
+    match speed_type {
+        SpeedType::Pace => "Pace",
+        SpeedType::Speed => "Speed",
+    }
+}
+
+fn course_point_type_name(point_type: &CoursePointType) -> &'static str {
+    match point_type {
+        CoursePointType::Generic => "Generic",
+        CoursePointType::Summit => "Summit",
+        CoursePointType::Valley => "Valley",
+        CoursePointType::Water => "Water",
+        CoursePointType::Food => "Food",
+        CoursePointType::Danger => "Danger",
+        CoursePointType::Left => "Left",
+        CoursePointType::Right => "Right",
+        CoursePointType::Straight => "Straight",
+        CoursePointType::FirstAid => "First Aid",
+        CoursePointType::Category4 => "4th Category",
+        CoursePointType::Category3 => "3rd Category",
+        CoursePointType::Category2 => "2nd Category",
+        CoursePointType::Category1 => "1st Category",
+        CoursePointType::HorsCategory => "Hors Category",
+        CoursePointType::Sprint => "Sprint",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_tcx_string_test() {
+        for tcx_bytes in [
+            include_bytes!("../test_resources/test.tcx.xml").as_slice(),
+            include_bytes!("../test_resources/biking.tcx.xml").as_slice(),
+            include_bytes!("../test_resources/course.tcx.xml").as_slice(),
+        ] {
+            let tc_db = crate::read(tcx_bytes).unwrap();
+            let tcx = tc_db.to_tcx_string().unwrap();
+            assert!(tcx.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+            assert_eq!(tc_db, crate::read_str(&tcx).unwrap());
+        }
+
+        let extension = ActivityLapExtension {
+            avg_speed: Some(4.5),
+            max_speed: Some(5.0),
+            ..ActivityLapExtension::default()
+        };
+        let tc_db = TrainingCenterDatabase {
+            activity_list: Some(ActivityList {
+                activities: vec![Activity {
+                    laps: vec![ActivityLap {
+                        extension: Some(extension.clone()),
+                        ..ActivityLap::default()
+                    }],
+                    ..Activity::default()
+                }],
+                ..ActivityList::default()
+            }),
+            ..TrainingCenterDatabase::default()
+        };
+        let tcx = tc_db.to_tcx_string().unwrap();
+        let activities = crate::read_str(&tcx)
+            .unwrap()
+            .activity_list
+            .unwrap()
+            .activities;
+        assert_eq!(Some(extension), activities[0].laps[0].extension);
+    }
+
+    #[test]
+    fn write_multi_sport_session_test() {
+        let biking = include_str!("../test_resources/biking.tcx.xml");
+        let tcx = biking
+            .replacen(
+                "</Activity>",
+                "</Activity></NextSport><Notes>Duathlon</Notes></MultiSportSession>",
+                1,
+            )
+            .replacen(
+                r#"<Activity Sport="Biking">"#,
+                r#"<MultiSportSession>
+                    <Id>2023-03-26T11:00:00Z</Id>
+                    <FirstSport>
+                        <Activity Sport="Running">
+                            <Id>2023-03-26T11:00:00Z</Id>
+                        </Activity>
+                    </FirstSport>
+                    <NextSport>
+                        <Transition StartTime="2023-03-26T11:42:00Z">
+                            <TotalTimeSeconds>124.0</TotalTimeSeconds>
+                            <DistanceMeters>150.0</DistanceMeters>
+                            <Calories>8</Calories>
+                            <Intensity>Active</Intensity>
+                            <TriggerMethod>Manual</TriggerMethod>
+                        </Transition>
+                        <Activity Sport="Biking">"#,
+                1,
+            );
+        let tc_db = crate::read_str(&tcx).unwrap();
+        let written = tc_db.to_tcx_string().unwrap();
+        assert!(written.contains("<Transition StartTime=\"2023-03-26T11:42:00Z\">"));
+        let read_back = crate::read_str(&written).unwrap();
+        assert_eq!(tc_db, read_back);
+        let session = &read_back.activity_list.unwrap().multi_sport_sessions[0];
+        assert_eq!(2, session.sports.as_ref().unwrap().len());
+        assert_eq!(Some("Duathlon"), session.notes.as_deref());
+    }
+
+    #[test]
+    fn write_escaped_notes_test() {
+        let tc_db = TrainingCenterDatabase {
+            activity_list: Some(ActivityList {
+                activities: vec![Activity {
+                    notes: Some("Great run & fast <PR>".to_string()),
+                    ..Activity::default()
+                }],
+                ..ActivityList::default()
+            }),
+            ..TrainingCenterDatabase::default()
+        };
+        let tcx = tc_db.to_tcx_string().unwrap();
+        assert!(tcx.contains("<Notes>Great run &amp; fast &lt;PR&gt;</Notes>"));
+        let activity = &crate::read_str(&tcx)
+            .unwrap()
+            .activity_list
+            .unwrap()
+            .activities[0];
+        assert_eq!(Some("Great run & fast <PR>"), activity.notes.as_deref());
+    }
+
+    #[test]
+    fn write_decimal_notation_test() {
+        let tc_db = TrainingCenterDatabase {
+            activity_list: Some(ActivityList {
+                activities: vec![Activity {
+                    laps: vec![ActivityLap {
+                        total_time_seconds: 1e-7,
+                        distance_meters: 1e21,
+                        ..ActivityLap::default()
+                    }],
+                    ..Activity::default()
+                }],
+                ..ActivityList::default()
+            }),
+            ..TrainingCenterDatabase::default()
+        };
+        let tcx = tc_db.to_tcx_string().unwrap();
+        assert!(tcx.contains("<TotalTimeSeconds>0.0000001</TotalTimeSeconds>"));
+        assert!(tcx.contains("<DistanceMeters>1000000000000000000000</DistanceMeters>"));
+    }
+
+    #[test]
+    fn write_workout_test() {
+        let workout = Workout {
+            name: Some("Intervals".to_string()),
+            sport: Some(Sport::Running),
+            steps: Some(vec![
+                StepType::Step(Step {
+                    duration: Some(Duration::Time(600)),
+                    target: Some(Target::None),
+                    ..Step::default()
+                }),
+                StepType::Repeat(Repeat {
+                    step_id: Some(2),
+                    repetitions: Some(4),
+                    children: Some(vec![
+                        StepType::Step(Step {
+                            duration: Some(Duration::Distance(400)),
+                            target: Some(Target::Speed(Zone::CustomSpeedZone(CustomSpeedZone {
+                                view_as: Some(SpeedType::Pace),
+                                low_in_meters_per_second: Some(4.2),
+                                high_in_meters_per_second: Some(4.5),
+                            }))),
+                            ..Step::default()
+                        }),
+                        StepType::Step(Step {
+                            duration: Some(Duration::HeartRateBelow(120)),
+                            target: Some(Target::HeartRate(Zone::PredefinedHeartRateZone(1))),
+                            ..Step::default()
+                        }),
+                    ]),
+                }),
+            ]),
+            ..Workout::default()
+        };
+        let tc_db = TrainingCenterDatabase {
+            workout_list: Some(WorkoutList {
+                workouts: Some(vec![workout]),
+            }),
+            ..TrainingCenterDatabase::default()
+        };
+        let tcx = tc_db.to_tcx_string().unwrap();
+        assert_eq!(tc_db, crate::read_str(&tcx).unwrap());
+    }
+}